@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::fs;
+
+/// Returns user names by user ID from the local passwd database.
+///
+/// Only `/etc/passwd` is read, names of other NSS sources like LDAP are not
+/// resolved.
+pub fn users() -> HashMap<u32, String> {
+    read("/etc/passwd")
+}
+
+/// Returns group names by group ID from the local group database.
+///
+/// Only `/etc/group` is read, names of other NSS sources like LDAP are not
+/// resolved.
+pub fn groups() -> HashMap<u32, String> {
+    read("/etc/group")
+}
+
+/// Reads a file in `passwd` format, i.e. colon-separated fields with the
+/// name first and the ID third. Missing or unreadable files yield no names.
+fn read(file: &str) -> HashMap<u32, String> {
+    match fs::read_to_string(file) {
+        Ok(content) => parse(&content),
+
+        Err(error) => {
+            log::debug!("not resolving names, reading {file}: {error}");
+            HashMap::new()
+        }
+    }
+}
+
+fn parse(content: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+
+    for line in content.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split(':');
+        let name = fields.next();
+        let id = fields.nth(1).and_then(|id| id.parse().ok());
+
+        if let (Some(name), Some(id)) = (name, id) {
+            names.entry(id).or_insert_with(|| name.to_owned());
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passwd() {
+        let content = "\
+# comment
+root:x:0:0:root:/root:/bin/bash
+alice:x:1000:1000::/home/alice:/bin/bash
+toor:x:0:0:root:/root:/bin/bash
+broken
+";
+
+        let names = parse(content);
+
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&0], "root");
+        assert_eq!(names[&1000], "alice");
+    }
+}
@@ -1,3 +1,352 @@
 #[cfg(feature = "spectrum-scale")]
 pub mod spectrum_scale;
 pub mod universal;
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(target_family = "unix")]
+use std::os::unix::fs::MetadataExt;
+
+use crate::{Data, Filter, Key, Symlinks, Time};
+
+/// Options for analyzing a directory.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Thresholds in days.
+    pub ages_in_days: Vec<u64>,
+
+    /// Count files in disjoint age buckets instead of below cumulative
+    /// thresholds.
+    pub buckets: bool,
+
+    /// Point in time to measure ages against. Without one, the universal
+    /// traversal uses the time it starts and Spectrum Scale the time the
+    /// policy is evaluated.
+    pub reference_time: Option<SystemTime>,
+
+    /// Time dimensions to report. The universal directory traversal always
+    /// gathers all of them.
+    pub times: Vec<Time>,
+
+    /// Patterns to exclude or include files and directories.
+    pub filter: Filter,
+
+    /// Report subdirectories down to this depth separately.
+    pub depth: Option<usize>,
+
+    /// Count only files at least this deep below the input directory, i.e.
+    /// files directly in it have depth 1.
+    pub min_depth: Option<usize>,
+
+    /// Count only files at most this deep below the input directory, deeper
+    /// directories are not descended into.
+    pub max_depth: Option<usize>,
+
+    /// Count only files of at least this apparent size in bytes.
+    pub min_size: Option<u64>,
+
+    /// Count only files of at most this apparent size in bytes.
+    pub max_size: Option<u64>,
+
+    /// Break down data by file owner.
+    pub by_user: bool,
+
+    /// Break down data by file group.
+    pub by_group: bool,
+
+    /// Break down data by file extension.
+    pub by_extension: bool,
+
+    /// Break down data by the device the files are on, i.e. by mounted file
+    /// system.
+    pub by_device: bool,
+
+    /// Report only the extensions with the largest size separately, the
+    /// others are summed up as other extensions.
+    pub top_extensions: Option<usize>,
+
+    /// Break down data by file size. These are the upper bounds of all but
+    /// the last bucket in ascending order.
+    pub size_buckets: Vec<u64>,
+
+    /// Fail on the first error of a single entry, e.g. a file that vanished
+    /// during traversal, instead of counting it. Universal traversal only.
+    pub strict: bool,
+
+    /// Report the largest files past each age threshold, i.e. older than
+    /// the threshold in all time dimensions of [`Options::times`].
+    pub top: Option<usize>,
+
+    /// Do not cross file system boundaries. Universal traversal only.
+    pub one_file_system: bool,
+
+    /// Do not descend into mounts of these file system types, e.g. `nfs`.
+    /// Universal traversal on Linux only.
+    pub exclude_fs_types: Vec<String>,
+
+    /// Do not descend into mounts on these mount points. Universal traversal
+    /// on Linux only.
+    pub exclude_mounts: Vec<PathBuf>,
+
+    /// Whether to follow symbolic links. Universal traversal only.
+    pub symlinks: Symlinks,
+
+    /// Number of threads, `0` uses the number of logical CPUs. Universal
+    /// traversal only.
+    pub threads: usize,
+
+    /// Maximum number of `stat` calls per second of all threads together.
+    /// Universal traversal only.
+    pub max_stats_per_second: Option<u32>,
+
+    /// Maximum number of directories read per second of all threads
+    /// together. Universal traversal only.
+    pub max_dirs_per_second: Option<u32>,
+
+    /// Back off when the latency of `stat` calls rises. Universal traversal
+    /// only.
+    pub adaptive_throttle: bool,
+}
+
+impl Options {
+    /// Returns the subdirectory, relative to the input directory, to which
+    /// the contents of the directory `relative` are attributed, i.e. the
+    /// directory itself or its ancestor at the breakdown depth. Without a
+    /// breakdown, this is always the input directory, i.e. the empty path.
+    pub(crate) fn breakdown(&self, relative: &Path) -> PathBuf {
+        self.depth.map_or_else(PathBuf::new, |depth| {
+            relative.components().take(depth).collect()
+        })
+    }
+
+    /// Returns empty data with the ages of [`Options::ranges`].
+    pub(crate) fn data(&self) -> Data {
+        let ages: Vec<u64> =
+            self.ranges().iter().map(|range| range.0).collect();
+
+        Data::default().with_ages(&ages).with_buckets(self.buckets)
+    }
+
+    /// Returns the age ranges in days to count files in, as the age under
+    /// which they are stored in [`Data`], the inclusive minimum and the
+    /// exclusive maximum.
+    ///
+    /// Without buckets, these are the thresholds, i.e. from zero to each
+    /// age. With buckets, these are disjoint ranges from zero to the smallest
+    /// age, between adjacent ages, and from the largest age, stored under
+    /// their minimum.
+    pub(crate) fn ranges(&self) -> Vec<(u64, u64, Option<u64>)> {
+        let ages = self.ages();
+
+        if !self.buckets {
+            return ages.into_iter().map(|age| (age, 0, Some(age))).collect();
+        }
+
+        let mut ranges = vec![];
+        let mut min = 0;
+
+        for age in ages.into_iter().filter(|age| *age > 0) {
+            ranges.push((min, min, Some(age)));
+            min = age;
+        }
+
+        ranges.push((min, min, None));
+
+        ranges
+    }
+
+    /// Returns the thresholds in ascending order, without duplicates.
+    pub(crate) fn ages(&self) -> Vec<u64> {
+        let mut ages = self.ages_in_days.clone();
+        ages.sort_unstable();
+        ages.dedup();
+        ages
+    }
+
+    /// Returns `true` if files at `depth` below the input directory are
+    /// counted, see [`Options::min_depth`] and [`Options::max_depth`].
+    pub(crate) fn is_within_depth(&self, depth: usize) -> bool {
+        self.min_depth.is_none_or(|min| depth >= min)
+            && self.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    /// Returns `true` if files of apparent size `bytes` are counted, see
+    /// [`Options::min_size`] and [`Options::max_size`].
+    pub(crate) fn is_within_size(&self, bytes: u64) -> bool {
+        self.min_size.is_none_or(|min| bytes >= min)
+            && self.max_size.is_none_or(|max| bytes <= max)
+    }
+
+    /// Returns `true` if any breakdown of the data within a directory is
+    /// requested.
+    fn has_breakdown(&self) -> bool {
+        self.by_user
+            || self.by_group
+            || self.by_extension
+            || self.by_device
+            || !self.size_buckets.is_empty()
+    }
+
+    /// Returns the breakdown keys of a file named `name` with a size of
+    /// `bytes` owned by `uid` and `gid` on the device with ID `device`.
+    pub(crate) fn keys(
+        &self,
+        uid: u32,
+        gid: u32,
+        device: u64,
+        name: &OsStr,
+        bytes: u64,
+    ) -> Vec<Key> {
+        let mut keys = vec![];
+
+        if self.by_user {
+            keys.push(Key::User(uid));
+        }
+
+        if self.by_group {
+            keys.push(Key::Group(gid));
+        }
+
+        if self.by_extension {
+            keys.push(Key::Extension(extension(name)));
+        }
+
+        if !self.size_buckets.is_empty() {
+            keys.push(self.size_bucket(bytes));
+        }
+
+        if self.by_device {
+            keys.push(Key::Device(device));
+        }
+
+        keys
+    }
+
+    /// Returns the key of the size bucket containing `bytes`.
+    fn size_bucket(&self, bytes: u64) -> Key {
+        let i = self.size_buckets.partition_point(|max| *max <= bytes);
+
+        let min = i.checked_sub(1).map_or(0, |i| self.size_buckets[i]);
+        let max = self.size_buckets.get(i).copied();
+
+        Key::Size { min, max }
+    }
+
+    /// Sums up the data attributed to subdirectories, see
+    /// [`Options::breakdown`].
+    pub(crate) fn sum(&self, breakdown: HashMap<PathBuf, Data>) -> Data {
+        let mut data = self.data();
+
+        if self.has_breakdown() {
+            data = data.with_breakdown();
+        }
+
+        if self.depth.is_some() {
+            data += Data::roll_up(breakdown);
+        } else {
+            for directory in breakdown.into_values() {
+                data += directory;
+            }
+        }
+
+        if let Some(n) = self.top_extensions {
+            data.truncate_extensions(n);
+        }
+
+        data
+    }
+}
+
+/// Suffixes of compressed files, which are reported together with the
+/// preceding extension, e.g. `.fastq.gz`.
+const COMPRESSION: [&str; 6] = ["bz2", "gz", "lz4", "xz", "z", "zst"];
+
+/// Returns the lowercase extension of a file name including the dot, or an
+/// empty string if there is none.
+fn extension(name: &OsStr) -> String {
+    let path = Path::new(name);
+
+    let Some(extension) = path.extension() else {
+        return String::new();
+    };
+
+    let extension = extension.to_string_lossy().to_lowercase();
+
+    if COMPRESSION.contains(&extension.as_str()) {
+        let inner = path
+            .file_stem()
+            .and_then(|stem| Path::new(stem).extension());
+
+        if let Some(inner) = inner {
+            let inner = inner.to_string_lossy().to_lowercase();
+            return format!(".{inner}.{extension}");
+        }
+    }
+
+    format!(".{extension}")
+}
+
+/// Returns the ID of the device the file is on.
+#[cfg(target_family = "unix")]
+pub fn device(meta: &fs::Metadata) -> u64 {
+    meta.dev()
+}
+
+#[cfg(not(target_family = "unix"))]
+pub const fn device(_meta: &fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        let extension = |name: &str| extension(OsStr::new(name));
+
+        assert_eq!(extension("reads.bam"), ".bam");
+        assert_eq!(extension("R1.fastq.GZ"), ".fastq.gz");
+        assert_eq!(extension("sample.R1.fastq.gz"), ".fastq.gz");
+        assert_eq!(extension("archive.gz"), ".gz");
+        assert_eq!(extension("Makefile"), "");
+        assert_eq!(extension(".bashrc"), "");
+    }
+
+    #[test]
+    fn ranges() {
+        let mut options = Options {
+            ages_in_days: vec![365, 90],
+            ..Options::default()
+        };
+
+        assert_eq!(options.ranges(), [(90, 0, Some(90)), (365, 0, Some(365))]);
+
+        options.buckets = true;
+
+        assert_eq!(
+            options.ranges(),
+            [(0, 0, Some(90)), (90, 90, Some(365)), (365, 365, None)]
+        );
+    }
+
+    #[test]
+    fn size_buckets() {
+        let options = Options {
+            size_buckets: vec![4096, 1 << 20],
+            ..Options::default()
+        };
+
+        let bucket = |min, max| Key::Size { min, max };
+
+        assert_eq!(options.size_bucket(0), bucket(0, Some(4096)));
+        assert_eq!(options.size_bucket(4095), bucket(0, Some(4096)));
+        assert_eq!(options.size_bucket(4096), bucket(4096, Some(1 << 20)));
+        assert_eq!(options.size_bucket(1 << 30), bucket(1 << 20, None));
+    }
+}
@@ -1,31 +1,51 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ffi::OsStr;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::SystemTime;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use bstr::ByteSlice;
 use bstr::io::BufReadExt;
+use jiff::civil::DateTime;
+use jiff::tz::TimeZone;
 use tempfile::{tempdir, tempdir_in};
 
-use crate::Data;
+use crate::analysis::device;
+use crate::filter::Pattern;
+use crate::timestamp::format_timestamp;
+use crate::{Amount, Data, Filter, Key, Options, Time, TopFile};
 
 /// Runs `mmapplypolicy` on Spectrum Scale file systems.
 ///
 /// # Errors
 ///
 /// - creating tmp directory
+/// - translating filter patterns
 /// - writing policy file
 /// - running `mmapplypolicy`
 /// - parsing `mmapplypolicy` output
 pub fn run(
     dir: &str,
-    ages_in_days: &[u64],
+    options: &Options,
     nodes: Option<&str>,
+    thread_level: Option<usize>,
+    dir_thread_level: Option<usize>,
     local_work_dir: Option<&str>,
     global_work_dir: Option<&str>,
 ) -> Result<Data> {
+    // policies do not cross file systems, so all files are on this device
+    let device = device(&fs::metadata(dir)?);
+
+    let root = fs::canonicalize(dir)?;
+    let root = root
+        .to_str()
+        .ok_or_else(|| anyhow!("not valid UTF-8: {}", root.display()))?;
+
     let tmp = if let Some(local_work_dir) = local_work_dir {
         tempdir_in(local_work_dir)?
     } else {
@@ -36,7 +56,7 @@ pub fn run(
     let prefix = tmp.path().join("stor-age");
 
     let mut file = File::create(&policy)?;
-    write_policy(&mut file, ages_in_days)?;
+    write_policy(&mut file, root, options, &TimeZone::system())?;
     file.sync_all()?;
 
     let mut command = Command::new("mmapplypolicy");
@@ -52,6 +72,14 @@ pub fn run(
         command.args(["-N", nodes]);
     }
 
+    if let Some(thread_level) = thread_level {
+        command.args(["-m", &thread_level.to_string()]);
+    }
+
+    if let Some(dir_thread_level) = dir_thread_level {
+        command.args(["-n", &dir_thread_level.to_string()]);
+    }
+
     if let Some(local_work_dir) = local_work_dir {
         command.args(["-s", local_work_dir]);
     }
@@ -75,104 +103,558 @@ pub fn run(
     };
 
     if ecode.success() {
-        let total_file = tmp.path().join("stor-age.list.total");
-        let (tot_bytes, tot_files) = sum(&total_file)?;
+        parse(tmp.path(), Path::new(root), device, options)
+    } else {
+        Err(anyhow!("mmapplypolicy was no success"))
+    }
+}
 
-        let mut data = Data::default()
-            .with_ages(ages_in_days)
-            .with_total_bytes(tot_bytes)
-            .with_total_files(tot_files);
+/// Returns the data of the lists `mmapplypolicy` wrote to `dir`.
+fn parse(
+    dir: &Path,
+    root: &Path,
+    device: u64,
+    options: &Options,
+) -> Result<Data> {
+    let mut breakdown: HashMap<PathBuf, Data> = HashMap::new();
+    let empty = || options.data();
 
-        for age in ages_in_days {
-            let access_file =
-                tmp.path().join(format!("stor-age.list.access_{age}"));
+    let total_file = dir.join("stor-age.list.total");
 
-            let modify_file =
-                tmp.path().join(format!("stor-age.list.modify_{age}"));
+    for ((path, keys), total) in sum(&total_file, root, device, options)? {
+        let data = breakdown.entry(path).or_insert_with(empty);
+        let total = Data::default().with_total(total);
 
-            let (a_b, a_f) = sum(&access_file)?;
-            let (m_b, m_f) = sum(&modify_file)?;
+        for key in keys {
+            data.add_breakdown(key, total.clone());
+        }
 
-            data.insert(*age, a_b, m_b, a_f, m_f);
+        *data += total;
+    }
+
+    for (age, _, _) in options.ranges() {
+        for time in &options.times {
+            let list = list_name(*time);
+            let file = dir.join(format!("stor-age.list.{list}_{age}"));
+
+            for ((path, keys), amount) in sum(&file, root, device, options)? {
+                let data = breakdown.entry(path).or_insert_with(empty);
+
+                let mut young = Data::default();
+                young.insert(age, *time, amount);
+
+                for key in keys {
+                    data.add_breakdown(key, young.clone());
+                }
+
+                *data += young;
+            }
+        }
+    }
+
+    if let Some(n) = options.top {
+        let data = breakdown.entry(PathBuf::new()).or_insert_with(empty);
+
+        for age in options.ages() {
+            let file = dir.join(format!("stor-age.list.top_{age}"));
+            top(&file, age, n, &TimeZone::system(), data)?;
         }
+    }
 
-        Ok(data)
-    } else {
-        Err(anyhow!("mmapplypolicy was no success"))
+    Ok(options.sum(breakdown))
+}
+
+const fn list_name(time: Time) -> &'static str {
+    match time {
+        Time::Accessed => "access",
+        Time::Modified => "modify",
+        Time::Changed => "change",
+        Time::Created => "create",
     }
 }
 
-fn write_policy(mut w: impl io::Write, ages: &[u64]) -> io::Result<()> {
+const fn attribute(time: Time) -> &'static str {
+    match time {
+        Time::Accessed => "ACCESS_TIME",
+        Time::Modified => "MODIFICATION_TIME",
+        Time::Changed => "CHANGE_TIME",
+        Time::Created => "CREATION_TIME",
+    }
+}
+
+/// Writes the policy, with the reference time as civil time in `tz`, since
+/// `mmapplypolicy` evaluates timestamps in local time.
+fn write_policy(
+    mut w: impl io::Write,
+    root: &str,
+    options: &Options,
+    tz: &TimeZone,
+) -> Result<()> {
+    let ranges = options.ranges();
+    let times = &options.times;
+    let show = show(options);
+
+    let now = options.reference_time.map_or_else(
+        || String::from("CURRENT_TIMESTAMP"),
+        |time| format!("TIMESTAMP('{}')", format_timestamp(time, tz)),
+    );
+
+    writeln!(w)?;
+
+    for time in times {
+        let list = list_name(*time);
+        let attribute = attribute(*time);
+
+        writeln!(w, "define({list}_age, (DAYS({now}) - DAYS({attribute})))")?;
+    }
+
     write!(
         w,
         "
-define(access_age, (DAYS(CURRENT_TIMESTAMP) - DAYS(ACCESS_TIME)))
-define(modify_age, (DAYS(CURRENT_TIMESTAMP) - DAYS(MODIFICATION_TIME)))
-
 RULE EXTERNAL LIST 'total' EXEC ''
 ",
     )?;
 
-    for age in ages {
-        write!(
-            w,
-            "
-RULE EXTERNAL LIST 'access_{age}' EXEC ''
-RULE EXTERNAL LIST 'modify_{age}' EXEC ''
-"
-        )?;
+    for (age, _, _) in &ranges {
+        writeln!(w)?;
+
+        for time in times {
+            let list = list_name(*time);
+            writeln!(w, "RULE EXTERNAL LIST '{list}_{age}' EXEC ''")?;
+        }
+    }
+
+    if options.top.is_some() {
+        writeln!(w)?;
+
+        for age in options.ages() {
+            writeln!(w, "RULE EXTERNAL LIST 'top_{age}' EXEC ''")?;
+        }
     }
 
+    write_filter(&mut w, root, &options.filter)?;
+    write_depth(&mut w, root, options)?;
+    write_size(&mut w, options)?;
+
     write!(
         w,
         "
 RULE
   LIST 'total'
-  SHOW(VARCHAR(FILE_SIZE))
+  SHOW({show})
 ",
     )?;
 
-    for age in ages {
+    for (age, min, max) in &ranges {
+        for time in times {
+            let list = list_name(*time);
+
+            let mut conditions = vec![];
+
+            if *min > 0 {
+                conditions.push(format!("{list}_age >= {min}"));
+            }
+
+            if let Some(max) = max {
+                conditions.push(format!("{list}_age < {max}"));
+            }
+
+            let conditions = conditions.join(" AND ");
+
+            let rule = if conditions.is_empty() {
+                format!("RULE\n  LIST '{list}_{age}'\n    SHOW({show})\n")
+            } else {
+                format!(
+                    "RULE\n  LIST '{list}_{age}'\n    SHOW({show})\n    \
+                     WHERE ({conditions})\n"
+                )
+            };
+
+            write!(w, "\n{rule}")?;
+        }
+    }
+
+    if options.top.is_some() {
+        let show = [
+            "VARCHAR(FILE_SIZE)",
+            "VARCHAR(ACCESS_TIME)",
+            "VARCHAR(MODIFICATION_TIME)",
+        ]
+        .join(" || ' ' || ");
+
+        for age in options.ages() {
+            let conditions: Vec<String> = times
+                .iter()
+                .map(|time| format!("{}_age >= {age}", list_name(*time)))
+                .collect();
+
+            write!(
+                w,
+                "
+RULE
+  LIST 'top_{age}'
+    SHOW({show})
+    WHERE ({})
+",
+                conditions.join(" AND ")
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the attributes to show in the lists, see [`sum`].
+fn show(options: &Options) -> String {
+    let mut show = vec!["VARCHAR(FILE_SIZE)", "VARCHAR(KB_ALLOCATED)"];
+
+    if by_owner(options) {
+        show.push("VARCHAR(USER_ID)");
+        show.push("VARCHAR(GROUP_ID)");
+    }
+
+    show.join(" || ' ' || ")
+}
+
+/// Returns `true` if the lists need to show file owners.
+const fn by_owner(options: &Options) -> bool {
+    options.by_user || options.by_group
+}
+
+/// Writes `EXCLUDE` rules, which apply to all following `LIST` rules.
+fn write_filter(
+    mut w: impl io::Write,
+    root: &str,
+    filter: &Filter,
+) -> Result<()> {
+    let root = like(root, false)?;
+
+    if !filter.excludes().is_empty() {
+        let mut clauses = vec![];
+
+        for pattern in filter.excludes() {
+            clauses.extend(exclude_clauses(&root, pattern)?);
+        }
+
         write!(
             w,
             "
 RULE
-  LIST 'access_{age}'
-    SHOW(VARCHAR(FILE_SIZE))
-    WHERE (access_age < {age})
+  EXCLUDE
+    WHERE {}
+",
+            clauses.join("\n       OR ")
+        )?;
+    }
+
+    if !filter.includes().is_empty() {
+        let mut clauses = vec![];
+
+        for pattern in filter.includes() {
+            clauses.push(include_clause(&root, pattern)?);
+        }
 
+        write!(
+            w,
+            "
 RULE
-  LIST 'modify_{age}'
-    SHOW(VARCHAR(FILE_SIZE))
-    WHERE (modify_age < {age})
-"
+  EXCLUDE
+    WHERE NOT ({})
+",
+            clauses.join("\n            OR ")
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes an `EXCLUDE` rule for files outside the depth limits. A file at
+/// depth `n` has `n` slashes after the input directory in its path.
+fn write_depth(
+    mut w: impl io::Write,
+    root: &str,
+    options: &Options,
+) -> Result<()> {
+    let root = like(root, false)?;
+    let mut clauses = vec![];
+
+    // every file is at least at depth 1, i.e. 0 is no limit
+    if let Some(min) = options.min_depth.filter(|min| *min > 0) {
+        let pattern = "/%".repeat(min);
+        clauses.push(format!("PATH_NAME NOT LIKE '{root}{pattern}'{ESCAPE}"));
+    }
+
+    if let Some(max) = options.max_depth {
+        let pattern = "/%".repeat(max + 1);
+        clauses.push(format!("PATH_NAME LIKE '{root}{pattern}'{ESCAPE}"));
+    }
+
+    if !clauses.is_empty() {
+        write!(
+            w,
+            "
+RULE
+  EXCLUDE
+    WHERE {}
+",
+            clauses.join("\n       OR ")
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_size(mut w: impl io::Write, options: &Options) -> Result<()> {
+    let mut clauses = vec![];
+
+    if let Some(min) = options.min_size {
+        clauses.push(format!("FILE_SIZE < {min}"));
+    }
+
+    if let Some(max) = options.max_size {
+        clauses.push(format!("FILE_SIZE > {max}"));
+    }
+
+    if !clauses.is_empty() {
+        write!(
+            w,
+            "
+RULE
+  EXCLUDE
+    WHERE {}
+",
+            clauses.join("\n       OR ")
         )?;
     }
 
     Ok(())
 }
 
-fn sum(file: &Path) -> Result<(u64, u64)> {
-    let mut sum_bytes = 0;
-    let mut sum_files = 0;
+/// Returns clauses matching files that match the pattern or that are below
+/// directories that match the pattern.
+fn exclude_clauses(root: &str, pattern: &Pattern) -> Result<Vec<String>> {
+    let glob = like(pattern.glob(), true)?;
 
-    if file.exists() {
-        let file = File::open(file)?;
-        let file = BufReader::new(file);
+    let clauses = if pattern.is_path() {
+        vec![
+            format!("PATH_NAME LIKE '{root}/{glob}'{ESCAPE}"),
+            format!("PATH_NAME LIKE '{root}/{glob}/%'{ESCAPE}"),
+        ]
+    } else {
+        vec![
+            format!("NAME LIKE '{glob}'{ESCAPE}"),
+            format!("PATH_NAME LIKE '{root}/{glob}/%'{ESCAPE}"),
+            format!("PATH_NAME LIKE '{root}/%/{glob}/%'{ESCAPE}"),
+        ]
+    };
+
+    Ok(clauses)
+}
+
+/// Returns clause matching files that match the pattern.
+fn include_clause(root: &str, pattern: &Pattern) -> Result<String> {
+    let glob = like(pattern.glob(), true)?;
+
+    let clause = if pattern.is_path() {
+        format!("PATH_NAME LIKE '{root}/{glob}'{ESCAPE}")
+    } else {
+        format!("NAME LIKE '{glob}'{ESCAPE}")
+    };
+
+    Ok(clause)
+}
+
+const ESCAPE: &str = " ESCAPE '\\'";
+
+/// Translates to a `LIKE` pattern. If `glob` is set, the wildcards `*` and
+/// `?` are translated, otherwise `s` is taken literally.
+fn like(s: &str, glob: bool) -> Result<String> {
+    let mut pattern = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if glob => pattern.push('%'),
+            '?' if glob => pattern.push('_'),
 
-        for line in file.byte_lines() {
-            let line = line?;
+            '[' | ']' | '{' | '}' if glob => {
+                return Err(anyhow!(
+                    "pattern not supported by mmapplypolicy: {s}"
+                ));
+            }
 
-            let size = line.splitn_str(6, " ").nth(4).unwrap();
-            let size = size.to_str().unwrap();
-            let size: u64 = size.parse().unwrap();
+            '\\' if glob => {
+                if let Some(c) = chars.next() {
+                    push_literal(&mut pattern, c);
+                }
+            }
 
-            sum_bytes += size;
-            sum_files += 1;
+            c => push_literal(&mut pattern, c),
         }
     }
 
-    Ok((sum_bytes, sum_files))
+    Ok(pattern)
+}
+
+fn push_literal(pattern: &mut String, c: char) {
+    match c {
+        '%' | '_' | '\\' => {
+            pattern.push('\\');
+            pattern.push(c);
+        }
+
+        '\'' => pattern.push_str("''"),
+
+        c => pattern.push(c),
+    }
+}
+
+/// Adds the files of a list to the `n` largest files past `age`.
+fn top(
+    list: &Path,
+    age: u64,
+    n: usize,
+    tz: &TimeZone,
+    data: &mut Data,
+) -> Result<()> {
+    if !list.exists() {
+        return Ok(());
+    }
+
+    let file = File::open(list)?;
+    let file = BufReader::new(file);
+
+    for (i, line) in file.byte_lines().enumerate() {
+        let file = top_file(&line?, tz).with_context(|| invalid(list, i))?;
+        data.add_top(age, n, file);
+    }
+
+    Ok(())
+}
+
+/// Returns the file of a line of a top list.
+///
+/// `mmapplypolicy` shows timestamps in local time, i.e. date and time
+/// separated by a space without an offset, which are converted from `tz`.
+fn top_file(line: &[u8], tz: &TimeZone) -> Result<TopFile> {
+    let time = |date: &[u8], clock: &[u8]| {
+        let datetime: DateTime =
+            format!("{}T{}", date.to_str().ok()?, clock.to_str().ok()?)
+                .parse()
+                .ok()?;
+
+        tz.to_timestamp(datetime).ok().map(SystemTime::from)
+    };
+
+    let mut fields = line.splitn_str(10, " ").skip(4);
+
+    let bytes = number(&mut fields)?;
+    let accessed = time(field(&mut fields)?, field(&mut fields)?);
+    let modified = time(field(&mut fields)?, field(&mut fields)?);
+    let path = path(&mut fields)?.into_owned();
+
+    Ok(TopFile {
+        path,
+        bytes,
+        accessed,
+        modified,
+    })
+}
+
+/// Sums up a list by the subdirectory the files are attributed to, see
+/// [`Options::breakdown`], and by breakdown keys, see [`Options::keys`].
+fn sum(
+    list: &Path,
+    root: &Path,
+    device: u64,
+    options: &Options,
+) -> Result<HashMap<(PathBuf, Vec<Key>), Amount>> {
+    let mut sum: HashMap<(PathBuf, Vec<Key>), Amount> = HashMap::new();
+
+    if !list.exists() {
+        return Ok(sum);
+    }
+
+    let file = File::open(list)?;
+    let file = BufReader::new(file);
+
+    for (i, line) in file.byte_lines().enumerate() {
+        let (key, amount) = sum_file(&line?, root, device, options)
+            .with_context(|| invalid(list, i))?;
+
+        *sum.entry(key).or_default() += amount;
+    }
+
+    Ok(sum)
+}
+
+/// Returns the key to sum up the file of a line of a list by, and its
+/// amount.
+fn sum_file(
+    line: &[u8],
+    root: &Path,
+    device: u64,
+    options: &Options,
+) -> Result<((PathBuf, Vec<Key>), Amount)> {
+    let n = if by_owner(options) { 9 } else { 7 };
+    let mut fields = line.splitn_str(n, " ").skip(4);
+
+    let size = number(&mut fields)?;
+    let allocated: u64 = number(&mut fields)?;
+
+    let (uid, gid) = if by_owner(options) {
+        (number(&mut fields)?, number(&mut fields)?)
+    } else {
+        (0, 0)
+    };
+
+    let path = path(&mut fields)?;
+    let relative = path.strip_prefix(root).unwrap_or(&path);
+    let parent = relative.parent().unwrap_or_else(|| Path::new(""));
+
+    let name = path.file_name().unwrap_or_default();
+    let keys = options.keys(uid, gid, device, name, size);
+
+    let amount = Amount {
+        bytes: size,
+        allocated: allocated * 1024,
+        files: 1,
+    };
+
+    Ok(((options.breakdown(parent), keys), amount))
+}
+
+/// Returns the next space-separated field of a list line.
+fn field<'a>(fields: &mut impl Iterator<Item = &'a [u8]>) -> Result<&'a [u8]> {
+    fields.next().ok_or_else(|| anyhow!("missing field"))
+}
+
+/// Returns the next field of a list line as a number.
+fn number<'a, T: FromStr>(
+    fields: &mut impl Iterator<Item = &'a [u8]>,
+) -> Result<T> {
+    let field = field(fields)?;
+
+    field
+        .to_str()
+        .ok()
+        .and_then(|field| field.parse().ok())
+        .ok_or_else(|| anyhow!("invalid number: {}", field.as_bstr()))
+}
+
+/// Returns the path of a list line, which is its last field.
+fn path<'a>(
+    fields: &mut impl Iterator<Item = &'a [u8]>,
+) -> Result<Cow<'a, Path>> {
+    let path = field(fields)?;
+    let path = path.strip_prefix(b"-- ").unwrap_or(path);
+
+    Ok(path.to_path_lossy())
+}
+
+/// Returns the context of an error in a line of a list.
+fn invalid(list: &Path, i: usize) -> String {
+    format!("invalid line {} of {}", i + 1, list.display())
 }
 
 #[cfg(test)]
@@ -181,10 +663,14 @@ mod tests {
 
     #[test]
     fn policy() {
-        let ages = vec![90, 365];
+        let options = Options {
+            ages_in_days: vec![90, 365],
+            times: vec![Time::Accessed, Time::Modified],
+            ..Options::default()
+        };
 
         let mut result = vec![];
-        write_policy(&mut result, &ages).unwrap();
+        write_policy(&mut result, "/data", &options, &TimeZone::UTC).unwrap();
 
         let result = std::str::from_utf8(&result).unwrap();
 
@@ -202,29 +688,552 @@ RULE EXTERNAL LIST 'modify_365' EXEC ''
 
 RULE
   LIST 'total'
-  SHOW(VARCHAR(FILE_SIZE))
+  SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
 
 RULE
   LIST 'access_90'
-    SHOW(VARCHAR(FILE_SIZE))
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
     WHERE (access_age < 90)
 
 RULE
   LIST 'modify_90'
-    SHOW(VARCHAR(FILE_SIZE))
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
     WHERE (modify_age < 90)
 
 RULE
   LIST 'access_365'
-    SHOW(VARCHAR(FILE_SIZE))
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
     WHERE (access_age < 365)
 
 RULE
   LIST 'modify_365'
-    SHOW(VARCHAR(FILE_SIZE))
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
     WHERE (modify_age < 365)
 ";
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn policy_changed_created() {
+        let options = Options {
+            ages_in_days: vec![30],
+            times: vec![Time::Changed, Time::Created],
+            ..Options::default()
+        };
+
+        let mut result = vec![];
+        write_policy(&mut result, "/data", &options, &TimeZone::UTC).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        let expected = "
+define(change_age, (DAYS(CURRENT_TIMESTAMP) - DAYS(CHANGE_TIME)))
+define(create_age, (DAYS(CURRENT_TIMESTAMP) - DAYS(CREATION_TIME)))
+
+RULE EXTERNAL LIST 'total' EXEC ''
+
+RULE EXTERNAL LIST 'change_30' EXEC ''
+RULE EXTERNAL LIST 'create_30' EXEC ''
+
+RULE
+  LIST 'total'
+  SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+
+RULE
+  LIST 'change_30'
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+    WHERE (change_age < 30)
+
+RULE
+  LIST 'create_30'
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+    WHERE (create_age < 30)
+";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn policy_buckets() {
+        let options = Options {
+            ages_in_days: vec![90, 30],
+            buckets: true,
+            times: vec![Time::Accessed],
+            ..Options::default()
+        };
+
+        let mut result = vec![];
+        write_policy(&mut result, "/data", &options, &TimeZone::UTC).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        let expected = "
+define(access_age, (DAYS(CURRENT_TIMESTAMP) - DAYS(ACCESS_TIME)))
+
+RULE EXTERNAL LIST 'total' EXEC ''
+
+RULE EXTERNAL LIST 'access_0' EXEC ''
+
+RULE EXTERNAL LIST 'access_30' EXEC ''
+
+RULE EXTERNAL LIST 'access_90' EXEC ''
+
+RULE
+  LIST 'total'
+  SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+
+RULE
+  LIST 'access_0'
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+    WHERE (access_age < 30)
+
+RULE
+  LIST 'access_30'
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+    WHERE (access_age >= 30 AND access_age < 90)
+
+RULE
+  LIST 'access_90'
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED))
+    WHERE (access_age >= 90)
+";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn policy_reference_time() {
+        let options = Options {
+            ages_in_days: vec![30],
+            reference_time: Some(
+                crate::parse_timestamp("2024-01-31T12:00:00Z").unwrap(),
+            ),
+            times: vec![Time::Accessed],
+            ..Options::default()
+        };
+
+        let tz = TimeZone::fixed(jiff::tz::offset(1));
+
+        let mut result = vec![];
+        write_policy(&mut result, "/data", &options, &tz).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        assert!(result.starts_with(
+            "
+define(access_age, (DAYS(TIMESTAMP('2024-01-31 13:00:00')) - DAYS(ACCESS_TIME)))
+"
+        ));
+    }
+
+    #[test]
+    fn policy_top() {
+        let options = Options {
+            ages_in_days: vec![90],
+            times: vec![Time::Accessed, Time::Modified],
+            top: Some(10),
+            ..Options::default()
+        };
+
+        let mut result = vec![];
+        write_policy(&mut result, "/data", &options, &TimeZone::UTC).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        assert!(result.contains("RULE EXTERNAL LIST 'top_90' EXEC ''\n"));
+
+        assert!(result.ends_with(
+            "
+RULE
+  LIST 'top_90'
+    SHOW(VARCHAR(FILE_SIZE) || ' ' || VARCHAR(ACCESS_TIME) || ' ' || VARCHAR(MODIFICATION_TIME))
+    WHERE (access_age >= 90 AND modify_age >= 90)
+"
+        ));
+    }
+
+    #[test]
+    fn policy_filter() {
+        let filter = Filter::default()
+            .with_exclude(".snapshots")
+            .unwrap()
+            .with_exclude("/scratch/*")
+            .unwrap()
+            .with_include("*.fastq.gz")
+            .unwrap();
+
+        let mut result = vec![];
+        write_filter(&mut result, "/data/my_project", &filter).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        let expected = r"
+RULE
+  EXCLUDE
+    WHERE NAME LIKE '.snapshots' ESCAPE '\'
+       OR PATH_NAME LIKE '/data/my\_project/.snapshots/%' ESCAPE '\'
+       OR PATH_NAME LIKE '/data/my\_project/%/.snapshots/%' ESCAPE '\'
+       OR PATH_NAME LIKE '/data/my\_project/scratch/%' ESCAPE '\'
+       OR PATH_NAME LIKE '/data/my\_project/scratch/%/%' ESCAPE '\'
+
+RULE
+  EXCLUDE
+    WHERE NOT (NAME LIKE '%.fastq.gz' ESCAPE '\')
+";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn policy_depth() {
+        let options = Options {
+            min_depth: Some(2),
+            max_depth: Some(3),
+            ..Options::default()
+        };
+
+        let mut result = vec![];
+        write_depth(&mut result, "/data/my_project", &options).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        let expected = r"
+RULE
+  EXCLUDE
+    WHERE PATH_NAME NOT LIKE '/data/my\_project/%/%' ESCAPE '\'
+       OR PATH_NAME LIKE '/data/my\_project/%/%/%/%' ESCAPE '\'
+";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn policy_min_depth_zero() {
+        let options = Options {
+            min_depth: Some(0),
+            ..Options::default()
+        };
+
+        let mut result = vec![];
+        write_policy(&mut result, "/data", &options, &TimeZone::UTC).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+        assert!(!result.contains("EXCLUDE"));
+    }
+
+    #[test]
+    fn policy_size() {
+        let options = Options {
+            min_size: Some(1024),
+            max_size: Some(1 << 20),
+            ..Options::default()
+        };
+
+        let mut result = vec![];
+        write_size(&mut result, &options).unwrap();
+
+        let result = std::str::from_utf8(&result).unwrap();
+
+        let expected = r"
+RULE
+  EXCLUDE
+    WHERE FILE_SIZE < 1024
+       OR FILE_SIZE > 1048576
+";
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn show_owner() {
+        let options = Options {
+            by_group: true,
+            ..Options::default()
+        };
+
+        assert_eq!(
+            show(&options),
+            "VARCHAR(FILE_SIZE) || ' ' || VARCHAR(KB_ALLOCATED) \
+             || ' ' || VARCHAR(USER_ID) || ' ' || VARCHAR(GROUP_ID)"
+        );
+    }
+
+    #[test]
+    fn like_unsupported() {
+        assert!(like("[ab].txt", true).is_err());
+        assert_eq!(like("it's", false).unwrap(), "it''s");
+    }
+
+    #[test]
+    fn sum_list() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "\
+12345 1 0  4097 8 -- /data/foo/a
+12346 1 0  0 0 -- /data/foo/b c
+12347 1 0  1024 16 -- /data/foo/d
+",
+        )
+        .unwrap();
+
+        let root = Path::new("/data/foo");
+
+        let expected = Amount {
+            bytes: 5121,
+            allocated: 24 * 1024,
+            files: 3,
+        };
+
+        let result = sum(&list, root, 0, &Options::default()).unwrap();
+        let expected = HashMap::from([((PathBuf::new(), vec![]), expected)]);
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn sum_list_breakdown() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "\
+12345 1 0  1 8 -- /data/foo/a
+12346 1 0  2 8 -- /data/foo/bar/b
+12347 1 0  4 8 -- /data/foo/bar/baz/c
+",
+        )
+        .unwrap();
+
+        let root = Path::new("/data/foo");
+
+        let options = Options {
+            depth: Some(1),
+            ..Options::default()
+        };
+
+        let amount = |bytes, files| Amount {
+            bytes,
+            allocated: files * 8 * 1024,
+            files,
+        };
+
+        let expected = HashMap::from([
+            ((PathBuf::new(), vec![]), amount(1, 1)),
+            ((PathBuf::from("bar"), vec![]), amount(6, 2)),
+        ]);
+
+        assert_eq!(sum(&list, root, 0, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn sum_list_extension() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "\
+12345 1 0  1 8 -- /data/foo/a.bam
+12346 1 0  2 8 -- /data/foo/b c.fastq.gz
+12347 1 0  4 8 -- /data/foo/d.BAM
+",
+        )
+        .unwrap();
+
+        let root = Path::new("/data/foo");
+
+        let options = Options {
+            by_extension: true,
+            ..Options::default()
+        };
+
+        let amount = |bytes, files| Amount {
+            bytes,
+            allocated: files * 8 * 1024,
+            files,
+        };
+
+        let key = |extension: &str| {
+            (PathBuf::new(), vec![Key::Extension(extension.into())])
+        };
+
+        let expected = HashMap::from([
+            (key(".bam"), amount(5, 2)),
+            (key(".fastq.gz"), amount(2, 1)),
+        ]);
+
+        assert_eq!(sum(&list, root, 0, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn top_list() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "\
+12345 1 0  1 2024-01-31 12:00:00.000000 2024-01-30 12:00:00.000000 -- /data/a
+12346 1 0  4 2024-01-31 12:00:00.000000 2024-01-30 12:00:00.000000 -- /data/b c
+12347 1 0  2 2024-01-31 12:00:00.000000 2024-01-30 12:00:00.000000 -- /data/d
+",
+        )
+        .unwrap();
+
+        let mut data = Data::default();
+        top(&list, 90, 2, &TimeZone::UTC, &mut data).unwrap();
+
+        let top = data.get_top();
+        let (age, files) = &top[0];
+
+        assert_eq!(*age, 90);
+        assert_eq!(files.len(), 2);
+
+        assert_eq!(files[0].path, Path::new("/data/b c"));
+        assert_eq!(files[0].bytes, 4);
+        assert_eq!(
+            files[0].modified,
+            Some(crate::parse_timestamp("2024-01-30T12:00:00Z").unwrap())
+        );
+
+        assert_eq!(files[1].path, Path::new("/data/d"));
+    }
+
+    #[test]
+    fn sum_list_truncated() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "12345 1 0  4097 8 -- /data/foo/a\n12346 1 0  0",
+        )
+        .unwrap();
+
+        let error = sum(&list, Path::new("/data/foo"), 0, &Options::default())
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!("invalid line 2 of {}", list.display())
+        );
+    }
+
+    #[test]
+    fn top_list_local_time() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "\
+12345 1 0  1 2024-01-31 12:00:00.000000 2024-01-30 12:00:00.000000 -- /data/a
+",
+        )
+        .unwrap();
+
+        let tz = TimeZone::fixed(jiff::tz::offset(2));
+
+        let mut data = Data::default();
+        top(&list, 90, 1, &tz, &mut data).unwrap();
+
+        let top = data.get_top();
+        let file = &top[0].1[0];
+
+        assert_eq!(
+            file.accessed,
+            Some(crate::parse_timestamp("2024-01-31T10:00:00Z").unwrap())
+        );
+    }
+
+    #[test]
+    fn sum_list_owner() {
+        let tmp = tempdir().unwrap();
+        let list = tmp.path().join("list");
+
+        std::fs::write(
+            &list,
+            "\
+12345 1 0  1 8 1000 100 -- /data/foo/a
+12346 1 0  2 8 1001 100 -- /data/foo/b c
+12347 1 0  4 8 1000 200 -- /data/foo/d
+",
+        )
+        .unwrap();
+
+        let root = Path::new("/data/foo");
+
+        let options = Options {
+            by_user: true,
+            by_group: true,
+            ..Options::default()
+        };
+
+        let amount = |bytes, files| Amount {
+            bytes,
+            allocated: files * 8 * 1024,
+            files,
+        };
+
+        let expected = HashMap::from([
+            (
+                (PathBuf::new(), vec![Key::User(1000), Key::Group(100)]),
+                amount(1, 1),
+            ),
+            (
+                (PathBuf::new(), vec![Key::User(1001), Key::Group(100)]),
+                amount(2, 1),
+            ),
+            (
+                (PathBuf::new(), vec![Key::User(1000), Key::Group(200)]),
+                amount(4, 1),
+            ),
+        ]);
+
+        assert_eq!(sum(&list, root, 0, &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn parse_lists_owner() {
+        let tmp = tempdir().unwrap();
+
+        let list = "\
+12345 1 0  1 8 1000 100 -- /data/foo/a
+12346 1 0  2 8 1001 100 -- /data/foo/b
+";
+
+        std::fs::write(tmp.path().join("stor-age.list.total"), list).unwrap();
+        std::fs::write(tmp.path().join("stor-age.list.access_30"), list)
+            .unwrap();
+
+        let options = Options {
+            ages_in_days: vec![30],
+            times: vec![Time::Accessed],
+            by_user: true,
+            ..Options::default()
+        };
+
+        let data =
+            parse(tmp.path(), Path::new("/data/foo"), 0, &options).unwrap();
+
+        let amount = |bytes, files| Amount {
+            bytes,
+            allocated: files * 8 * 1024,
+            files,
+        };
+
+        assert_eq!(data.get_total(), amount(3, 2));
+        assert_eq!(data.get(30, Time::Accessed), Some(amount(3, 2)));
+
+        let breakdown = data.get_breakdown().unwrap();
+
+        for (uid, bytes) in [(1000, 1), (1001, 2)] {
+            let user = &breakdown[&Key::User(uid)];
+            assert_eq!(user.get(30, Time::Accessed), Some(amount(bytes, 1)));
+        }
+    }
 }
@@ -1,7 +1,16 @@
-use std::collections::HashMap;
+mod checkpoint;
+mod long_path;
+mod queue;
+mod throttle;
+
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, ReadDir};
-use std::io::ErrorKind;
-use std::path::Path;
+use std::io::{self, ErrorKind};
+use std::num::NonZeroUsize;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
 use std::time::{Duration, SystemTime};
 
 #[cfg(target_family = "unix")]
@@ -9,24 +18,207 @@ use std::os::unix::fs::MetadataExt;
 
 use anyhow::Result;
 
-use crate::Data;
+use crate::analysis::device;
+use crate::mounts::{self, Mount};
+use crate::timestamp::days_before;
+use crate::{Amount, AtimeMode, Data, Options, Skipped, Time, TopFile};
+
+pub use self::checkpoint::Checkpoint;
+
+use self::queue::Queue;
+use self::throttle::Throttle;
+
+/// Hard-linked inodes already visited during traversal.
+///
+/// Share one instance across all directories of a run, so that files with
+/// multiple hard links are counted only once, even if the links are spread
+/// over different input directories.
+#[derive(Debug, Default)]
+pub struct HardLinks {
+    inodes: Inodes,
+}
+
+impl HardLinks {
+    /// Returns `true` if another hard link to the same inode has already been
+    /// visited, otherwise remembers the inode and adds it to `new`.
+    #[cfg(target_family = "unix")]
+    fn visited(&self, meta: &fs::Metadata, new: &mut Vec<(u64, u64)>) -> bool {
+        meta.nlink() > 1 && self.inodes.visited(meta, new)
+    }
+
+    #[cfg(not(target_family = "unix"))]
+    const fn visited(
+        &self,
+        _meta: &fs::Metadata,
+        _new: &mut Vec<(u64, u64)>,
+    ) -> bool {
+        false
+    }
+}
+
+/// Whether to follow symbolic links.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Symlinks {
+    /// Skip all symbolic links.
+    #[default]
+    Skip,
+
+    /// Follow all symbolic links.
+    Follow,
+
+    /// Follow only symbolic links whose target is inside the input directory.
+    FollowWithinRoot,
+}
+
+/// Set of `(device, inode)` pairs.
+#[derive(Debug, Default)]
+struct Inodes(Mutex<HashSet<(u64, u64)>>);
+
+impl Inodes {
+    /// Returns `true` if the inode has already been visited, otherwise
+    /// remembers the inode and adds it to `new`.
+    #[cfg(target_family = "unix")]
+    fn visited(&self, meta: &fs::Metadata, new: &mut Vec<(u64, u64)>) -> bool {
+        let inode = (meta.dev(), meta.ino());
+
+        let inserted = self
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(inode);
+
+        if inserted {
+            new.push(inode);
+        }
+
+        !inserted
+    }
+
+    #[cfg(not(target_family = "unix"))]
+    const fn visited(
+        &self,
+        _meta: &fs::Metadata,
+        _new: &mut Vec<(u64, u64)>,
+    ) -> bool {
+        false
+    }
+
+    fn extend(&self, inodes: Vec<(u64, u64)>) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(inodes);
+    }
+}
+
+struct Context<'a> {
+    root: &'a Path,
+    options: &'a Options,
+    thresholds: Vec<Threshold>,
+
+    /// Ages and the timestamps files must not be newer than to be among the
+    /// largest files past them, only used with [`Options::top`].
+    cold: Vec<(u64, SystemTime)>,
+
+    dev: Option<u64>,
+    hard_links: &'a HardLinks,
+
+    /// Canonical input directory, only used to restrict symlinks and to look
+    /// up mount points.
+    canonical_root: PathBuf,
+
+    /// Mount points not to descend into, see [`Options::exclude_fs_types`]
+    /// and [`Options::exclude_mounts`].
+    excluded_mounts: HashSet<PathBuf>,
+
+    /// Directories already visited, only used when following symlinks to
+    /// detect loops.
+    directories: Inodes,
+
+    throttle: Throttle,
+
+    checkpoint: Option<&'a Checkpoint>,
+}
+
+/// Found while reading a single directory. Subdirectories are queued and
+/// everything is recorded in the checkpoint only once the directory is read
+/// completely, so that a resumed run reads it again from the start.
+#[derive(Debug, Default)]
+struct Found {
+    subdirectories: Vec<PathBuf>,
+
+    /// Hard-linked inodes visited first.
+    inodes: Vec<(u64, u64)>,
+
+    /// Directories visited first, only used when following symlinks.
+    directories: Vec<(u64, u64)>,
+}
 
 /// Runs universal directory traversal.
 ///
+/// Subdirectories are traversed in parallel by worker threads, which share a
+/// queue of directories instead of recursing, so that neither the stack nor
+/// the number of open directory handles grow with the depth of the tree.
+///
+/// When following symlinks, every directory is visited only once, which
+/// prevents loops. Files reached by multiple paths are counted each time,
+/// unless they are hard links.
+///
+/// Errors of single entries, e.g. files that vanish during traversal, are
+/// counted in [`Data::get_errors`] instead, unless [`Options::strict`] is
+/// set.
+///
+/// With a checkpoint, the progress is saved periodically and a saved
+/// progress of the same input directory is continued.
+///
 /// # Errors
 ///
+/// - ages too far before the reference time
 /// - walking directory
 /// - reading inode metadata
 pub fn run(
     dir: &str,
-    ages_in_days: &[u64],
-    // ALLOW not needed on non-unix platforms
-    #[allow(unused_variables)] one_file_system: bool,
+    options: &Options,
+    hard_links: &HardLinks,
+    checkpoint: Option<&Checkpoint>,
 ) -> Result<Data> {
-    let thresholds = thresholds(ages_in_days);
+    let now = options.reference_time.unwrap_or_else(SystemTime::now);
+    let thresholds = thresholds(options, now)?;
+
+    let cold = options
+        .ages()
+        .into_iter()
+        .map(|age| Ok((age, days_before(now, age)?)))
+        .collect::<Result<_>>()?;
+
+    let atime_mode = atime_mode(dir);
+
+    let directories = Inodes::default();
+
+    if options.symlinks != Symlinks::Skip {
+        directories.visited(&fs::metadata(dir)?, &mut Vec::new());
+    }
+
+    let excluded_mounts = excluded_mounts(options);
+
+    let canonical_root =
+        if options.symlinks == Symlinks::Skip && excluded_mounts.is_empty() {
+            PathBuf::new()
+        } else {
+            fs::canonicalize(dir)?
+        };
+
+    let pending = checkpoint.map_or_else(
+        || vec![PathBuf::from(dir)],
+        |checkpoint| {
+            let (pending, visited) = checkpoint.start(dir);
+            directories.extend(visited);
+            pending
+        },
+    );
 
     #[cfg(target_family = "unix")]
-    let dev = if one_file_system {
+    let dev = if options.one_file_system {
         Some(fs::metadata(dir)?.dev())
     } else {
         None
@@ -35,105 +227,529 @@ pub fn run(
     #[cfg(not(target_family = "unix"))]
     let dev = None;
 
-    walk(Path::new(dir), &thresholds, ages_in_days, dev)
+    let context = Context {
+        root: Path::new(dir),
+        options,
+        thresholds,
+        cold,
+        dev,
+        hard_links,
+        canonical_root,
+        excluded_mounts,
+        directories,
+        throttle: Throttle::new(options),
+        checkpoint,
+    };
+
+    let threads = if options.threads == 0 {
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    } else {
+        options.threads
+    };
+
+    let queue = Queue::new(pending);
+
+    let mut breakdown = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| work(&queue, &context)))
+            .collect();
+
+        let mut breakdown: HashMap<PathBuf, Data> = HashMap::new();
+
+        for worker in workers {
+            let worker =
+                worker.join().unwrap_or_else(|e| panic::resume_unwind(e));
+
+            for (path, data) in worker {
+                *breakdown.entry(path).or_default() += data;
+            }
+        }
+
+        breakdown
+    });
+
+    queue.into_result()?;
+
+    if let Some(checkpoint) = checkpoint {
+        for (path, data) in checkpoint.take() {
+            *breakdown.entry(path).or_default() += data;
+        }
+    }
+
+    Ok(options.sum(breakdown).with_atime_mode(atime_mode))
 }
 
-fn thresholds(ages_in_days: &[u64]) -> HashMap<u64, SystemTime> {
-    let now = SystemTime::now();
+/// Returns the mount points not to descend into.
+fn excluded_mounts(options: &Options) -> HashSet<PathBuf> {
+    if options.exclude_fs_types.is_empty() && options.exclude_mounts.is_empty()
+    {
+        return HashSet::new();
+    }
+
+    let excluded = mounts::excluded(
+        mounts::mounts(),
+        &options.exclude_fs_types,
+        &options.exclude_mounts,
+    );
 
-    let mut thresholds = HashMap::with_capacity(ages_in_days.len());
+    log::debug!("excluded mounts: {excluded:?}");
 
-    for age in ages_in_days {
-        let duration = Duration::from_secs(60 * 60 * 24 * age);
-        let threshold = now - duration;
+    excluded
+}
+
+/// Returns how access times are updated on the file system of the input
+/// directory.
+fn atime_mode(dir: &str) -> Option<AtimeMode> {
+    let dir = fs::canonicalize(dir).ok()?;
+    let mounts = mounts::mounts();
+    let mode = mounts::containing(&mounts, &dir).map(Mount::atime_mode);
+
+    if let Some(mode) = mode {
+        log::debug!("{} is mounted {}", dir.display(), mode.name());
+    }
+
+    mode
+}
+
+/// Age range of [`Options::ranges`] as timestamps.
+struct Threshold {
+    /// Age under which matching files are stored.
+    age: u64,
+
+    /// Timestamps must not be newer, i.e. files are at least the minimum age.
+    not_after: Option<SystemTime>,
+
+    /// Timestamps must be newer, i.e. files are younger than the maximum age.
+    after: Option<SystemTime>,
+}
+
+impl Threshold {
+    fn contains(&self, timestamp: SystemTime) -> bool {
+        self.not_after
+            .is_none_or(|not_after| timestamp <= not_after)
+            && self.after.is_none_or(|after| timestamp > after)
+    }
+}
+
+fn thresholds(options: &Options, now: SystemTime) -> Result<Vec<Threshold>> {
+    options
+        .ranges()
+        .into_iter()
+        .map(|(age, min, max)| {
+            Ok(Threshold {
+                age,
+                not_after: (min > 0)
+                    .then(|| days_before(now, min))
+                    .transpose()?,
+                after: max.map(|max| days_before(now, max)).transpose()?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the data of the visited directories, grouped by the subdirectory
+/// they are attributed to.
+fn work(queue: &Queue, context: &Context) -> HashMap<PathBuf, Data> {
+    let mut directories = HashMap::new();
+
+    while let Some(task) = queue.pop() {
+        let dir = task.dir();
+        let relative = dir.strip_prefix(context.root).unwrap_or(dir);
+        let breakdown = context.options.breakdown(relative);
+
+        let mut data = context.options.data();
+        let mut found = Found::default();
+
+        let mut result = walk(dir, &mut data, &mut found, context);
+
+        if result.is_ok() {
+            if let Some(checkpoint) = context.checkpoint {
+                result = checkpoint.complete(dir, breakdown, data, &found);
+            } else {
+                *directories
+                    .entry(breakdown)
+                    .or_insert_with(|| context.options.data()) += data;
+            }
+        }
+
+        if result.is_ok() {
+            for subdirectory in found.subdirectories {
+                queue.push(subdirectory);
+            }
+        }
 
-        thresholds.insert(*age, threshold);
+        task.done(result);
     }
 
-    thresholds
+    directories
 }
 
 fn walk(
     dir: &Path,
-    thresholds: &HashMap<u64, SystemTime>,
-    ages_in_days: &[u64],
-    dev: Option<u64>,
-) -> Result<Data> {
-    let data = Data::default().with_ages(ages_in_days);
+    data: &mut Data,
+    found: &mut Found,
+    context: &Context,
+) -> Result<()> {
+    context.throttle.dir();
 
-    match fs::read_dir(dir) {
-        Ok(entries) => iterate(entries, data, thresholds, ages_in_days, dev),
+    match long_path::read_dir(dir) {
+        Ok(entries) => iterate(dir, entries, data, found, context),
 
         Err(error) if error.kind() == ErrorKind::PermissionDenied => {
             log::info!("skipping permission denied: {}", dir.display());
-            Ok(data)
+
+            *data += Data::default().with_skipped(Skipped {
+                permission_denied: 1,
+                ..Skipped::default()
+            });
+
+            Ok(())
         }
 
-        Err(error) => Err(error.into()),
+        Err(error) if dir == context.root => Err(error.into()),
+
+        Err(error) => tolerate(error, dir, data, context),
     }
 }
 
 fn iterate(
+    dir: &Path,
     entries: ReadDir,
-    mut data: Data,
-    thresholds: &HashMap<u64, SystemTime>,
-    ages_in_days: &[u64],
-    dev: Option<u64>,
-) -> Result<Data> {
+    data: &mut Data,
+    found: &mut Found,
+    context: &Context,
+) -> Result<()> {
     for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-        let meta = entry.metadata()?;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                tolerate(error, dir, data, context)?;
+                continue;
+            }
+        };
+
+        let path = dir.join(entry.file_name());
+        let relative = path.strip_prefix(context.root).unwrap_or(&path);
+
+        if context.options.filter.is_excluded(relative) {
+            log::debug!("skipping excluded: {}", path.display());
+            continue;
+        }
+
+        let mut meta = match context.throttle.stat(|| entry.metadata()) {
+            Ok(meta) => meta,
+            Err(error) => {
+                tolerate(error, &path, data, context)?;
+                continue;
+            }
+        };
+
+        if meta.file_type().is_symlink() {
+            if let Some(target) = follow(&path, context) {
+                log::debug!("following symlink: {}", path.display());
+
+                let followed = Data::default().with_followed_symlinks(1);
+                add(data, &path, &meta, followed, context);
+                meta = target;
+            } else {
+                let skipped = Data::default().with_skipped_symlinks(1);
+                add(data, &path, &meta, skipped, context);
+            }
+        }
+
         let file_type = meta.file_type();
 
-        if dev_check(dev, &meta) {
+        if dev_check(context.dev, &meta) {
             log::debug!("skipping different file system: {}", path.display());
-        } else if file_type.is_file() {
-            log::debug!("visiting: {}", path.display());
 
-            let bytes = meta.len();
+            let skipped = Data::default().with_skipped(Skipped {
+                other_file_system: 1,
+                ..Skipped::default()
+            });
 
-            let mut current =
-                Data::default().with_total_bytes(bytes).with_total_files(1);
+            add(data, &path, &meta, skipped, context);
+        } else if file_type.is_dir() && is_excluded_mount(relative, context) {
+            log::debug!("skipping excluded mount: {}", path.display());
 
-            for (age, threshold) in thresholds {
-                let (a_b, a_f) = if meta.accessed()? > *threshold {
-                    (bytes, 1)
-                } else {
-                    (0, 0)
-                };
+            let skipped = Data::default().with_skipped(Skipped {
+                other_file_system: 1,
+                ..Skipped::default()
+            });
 
-                let (m_b, m_f) = if meta.modified()? > *threshold {
-                    (bytes, 1)
-                } else {
-                    (0, 0)
-                };
+            add(data, &path, &meta, skipped, context);
+        } else if file_type.is_file()
+            && !context.options.filter.is_included(relative)
+        {
+            log::debug!("skipping not included: {}", path.display());
+        } else if file_type.is_file()
+            && !context.options.is_within_depth(depth(relative))
+        {
+            log::debug!("skipping outside depth limits: {}", path.display());
+        } else if file_type.is_file()
+            && !context.options.is_within_size(meta.len())
+        {
+            log::debug!("skipping outside size limits: {}", path.display());
+        } else if file_type.is_file()
+            && context.hard_links.visited(&meta, &mut found.inodes)
+        {
+            log::debug!("skipping visited hard link: {}", path.display());
 
-                current.insert(*age, a_b, m_b, a_f, m_f);
-            }
+            let hard_links = Data::default().with_hard_links(1);
+            add(data, &path, &meta, hard_links, context);
+        } else if file_type.is_file() {
+            log::debug!("visiting: {}", path.display());
 
-            data += current;
+            visit(data, &path, &meta, context)?;
+        } else if file_type.is_dir()
+            && context.options.symlinks != Symlinks::Skip
+            && context.directories.visited(&meta, &mut found.directories)
+        {
+            log::debug!("skipping visited directory: {}", path.display());
+        } else if file_type.is_dir()
+            && context
+                .options
+                .max_depth
+                .is_some_and(|max| depth(relative) >= max)
+        {
+            log::debug!("skipping below maximum depth: {}", path.display());
         } else if file_type.is_dir() {
             log::debug!("descending: {}", path.display());
 
-            data += walk(&path, thresholds, ages_in_days, dev)?;
+            found.subdirectories.push(path);
         } else {
             log::debug!(
                 "skipping neither regular file nor directory: {}",
                 path.display()
             );
+
+            // skipped symbolic links are already counted above
+            if !file_type.is_symlink() {
+                let skipped = Data::default().with_skipped(Skipped {
+                    special_files: 1,
+                    ..Skipped::default()
+                });
+
+                add(data, &path, &meta, skipped, context);
+            }
         }
     }
 
-    Ok(data)
+    Ok(())
+}
+
+/// Returns `true` if the directory, relative to the input directory, is the
+/// mount point of an excluded mount.
+fn is_excluded_mount(relative: &Path, context: &Context) -> bool {
+    !context.excluded_mounts.is_empty()
+        && context
+            .excluded_mounts
+            .contains(&context.canonical_root.join(relative))
+}
+
+/// Returns the depth of a path relative to the input directory.
+fn depth(relative: &Path) -> usize {
+    relative.components().count()
+}
+
+/// Counts an error of a single entry instead of failing the whole input
+/// directory, unless traversal is strict.
+fn tolerate(
+    error: io::Error,
+    path: &Path,
+    data: &mut Data,
+    context: &Context,
+) -> Result<()> {
+    if context.options.strict {
+        return Err(error.into());
+    }
+
+    log::info!("skipping {}: {error}", path.display());
+    data.add_error(error.kind());
+
+    Ok(())
+}
+
+/// Adds the data of an entry, also to the breakdown if requested.
+fn add(
+    data: &mut Data,
+    path: &Path,
+    meta: &fs::Metadata,
+    entry: Data,
+    context: &Context,
+) {
+    let name = path.file_name().unwrap_or_default();
+
+    let keys = context.options.keys(
+        uid(meta),
+        gid(meta),
+        device(meta),
+        name,
+        meta.len(),
+    );
+
+    for key in keys {
+        data.add_breakdown(key, entry.clone());
+    }
+
+    *data += entry;
+}
+
+/// Counts a regular file.
+fn visit(
+    data: &mut Data,
+    path: &Path,
+    meta: &fs::Metadata,
+    context: &Context,
+) -> Result<()> {
+    let amount = Amount {
+        bytes: meta.len(),
+        allocated: allocated(meta),
+        files: 1,
+    };
+
+    let mut current = Data::default().with_total(amount);
+    let mut timestamps = vec![];
+
+    for time in Time::ALL {
+        let Some(timestamp) = timestamp(meta, time)? else {
+            continue;
+        };
+
+        for threshold in &context.thresholds {
+            if threshold.contains(timestamp) {
+                current.insert(threshold.age, time, amount);
+            }
+        }
+
+        timestamps.push((time, timestamp));
+    }
+
+    add(data, path, meta, current, context);
+
+    if let Some(n) = context.options.top {
+        top(data, path, meta.len(), &timestamps, n, context);
+    }
+
+    Ok(())
+}
+
+/// Adds a file to the largest files past each age threshold it is past in
+/// all reported time dimensions. Unavailable time dimensions are ignored.
+fn top(
+    data: &mut Data,
+    path: &Path,
+    bytes: u64,
+    timestamps: &[(Time, SystemTime)],
+    n: usize,
+    context: &Context,
+) {
+    let get = |time| {
+        timestamps
+            .iter()
+            .find(|(t, _)| *t == time)
+            .map(|(_, timestamp)| *timestamp)
+    };
+
+    for (age, not_after) in &context.cold {
+        let cold = context.options.times.iter().all(|time| {
+            get(*time).is_none_or(|timestamp| timestamp <= *not_after)
+        });
+
+        if cold {
+            let file = TopFile {
+                path: path.to_owned(),
+                bytes,
+                accessed: get(Time::Accessed),
+                modified: get(Time::Modified),
+            };
+
+            data.add_top(*age, n, file);
+        }
+    }
+}
+
+/// Returns the metadata of the symlink target, if it should be followed.
+fn follow(path: &Path, context: &Context) -> Option<fs::Metadata> {
+    match context.options.symlinks {
+        Symlinks::Skip => None,
+
+        Symlinks::Follow => context.throttle.stat(|| fs::metadata(path)).ok(),
+
+        Symlinks::FollowWithinRoot => {
+            let target = fs::canonicalize(path).ok()?;
+
+            if target.starts_with(&context.canonical_root) {
+                context.throttle.stat(|| fs::metadata(target)).ok()
+            } else {
+                log::debug!("skipping symlink outside: {}", path.display());
+                None
+            }
+        }
+    }
 }
 
 #[cfg(target_family = "unix")]
 fn dev_check(dev: Option<u64>, meta: &fs::Metadata) -> bool {
-    dev.is_some_and(|dev| dev != meta.dev())
+    dev.is_some_and(|dev| dev != device(meta))
 }
 
 #[cfg(not(target_family = "unix"))]
 const fn dev_check(_dev: Option<u64>, _meta: &fs::Metadata) -> bool {
     false
 }
+
+#[cfg(target_family = "unix")]
+fn uid(meta: &fs::Metadata) -> u32 {
+    meta.uid()
+}
+
+#[cfg(not(target_family = "unix"))]
+const fn uid(_meta: &fs::Metadata) -> u32 {
+    0
+}
+
+#[cfg(target_family = "unix")]
+fn gid(meta: &fs::Metadata) -> u32 {
+    meta.gid()
+}
+
+#[cfg(not(target_family = "unix"))]
+const fn gid(_meta: &fs::Metadata) -> u32 {
+    0
+}
+
+/// Returns the allocated size in bytes.
+#[cfg(target_family = "unix")]
+fn allocated(meta: &fs::Metadata) -> u64 {
+    meta.blocks() * 512
+}
+
+#[cfg(not(target_family = "unix"))]
+fn allocated(meta: &fs::Metadata) -> u64 {
+    meta.len()
+}
+
+/// Returns the timestamp of the `time` dimension, if it is available.
+fn timestamp(meta: &fs::Metadata, time: Time) -> Result<Option<SystemTime>> {
+    let timestamp = match time {
+        Time::Accessed => Some(meta.accessed()?),
+        Time::Modified => Some(meta.modified()?),
+        Time::Changed => changed(meta),
+        Time::Created => meta.created().ok(),
+    };
+
+    Ok(timestamp)
+}
+
+#[cfg(target_family = "unix")]
+fn changed(meta: &fs::Metadata) -> Option<SystemTime> {
+    let secs = u64::try_from(meta.ctime()).ok()?;
+    let nanos = u32::try_from(meta.ctime_nsec()).ok()?;
+
+    SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+}
+
+#[cfg(not(target_family = "unix"))]
+const fn changed(_meta: &fs::Metadata) -> Option<SystemTime> {
+    None
+}
@@ -0,0 +1,1072 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::{FromStr, Split};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(target_family = "unix")]
+use std::ffi::OsString;
+#[cfg(target_family = "unix")]
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+use anyhow::{Context, Result, anyhow};
+
+use super::{Found, HardLinks, Inodes, Symlinks};
+use crate::filter::Pattern;
+use crate::{Amount, AtimeMode, Data, Key, Options, Skipped, Time, TopFile};
+
+/// First line of a checkpoint file, which identifies its format.
+const HEADER: &str = "stor-age checkpoint 1";
+
+/// Progress of a run, saved periodically to a file, so that an interrupted
+/// run can be resumed.
+///
+/// Input directories are saved once they are finished. Of the input
+/// directory in progress, the data of all completely read directories is
+/// saved together with the directories still waiting to be read, so that a
+/// resumed run skips all finished subtrees and produces the same report.
+///
+/// The file is a line-based text format. It is written to a temporary file
+/// first, which then replaces the previous checkpoint, so that an
+/// interruption while saving does not lose it.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    interval: Duration,
+
+    /// Options that affect the data, to detect resuming with other ones.
+    options: String,
+
+    /// Point in time ages are measured against, which is kept on resume.
+    now: SystemTime,
+
+    /// Number of largest files to keep, see [`Options::top`].
+    top: Option<usize>,
+
+    state: Mutex<State>,
+
+    /// Held while the file is written, outside of the lock of `state`.
+    file: Mutex<()>,
+}
+
+#[derive(Debug)]
+struct State {
+    saved: Instant,
+
+    /// Hard-linked inodes visited in completely read directories.
+    inodes: HashSet<(u64, u64)>,
+
+    /// Data of finished input directories.
+    finished: BTreeMap<String, Data>,
+
+    current: Option<Progress>,
+}
+
+/// Progress of the input directory currently traversed.
+#[derive(Debug)]
+struct Progress {
+    dir: String,
+
+    /// Data of completely read directories, grouped by the subdirectory
+    /// they are attributed to, see [`Options::breakdown`].
+    data: HashMap<PathBuf, Data>,
+
+    /// Directories waiting to be read.
+    pending: BTreeSet<PathBuf>,
+
+    /// Directories visited in completely read directories, only used when
+    /// following symlinks.
+    directories: HashSet<(u64, u64)>,
+}
+
+impl Progress {
+    fn new(dir: &str) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            data: HashMap::new(),
+            pending: BTreeSet::from([PathBuf::from(dir)]),
+            directories: HashSet::new(),
+        }
+    }
+}
+
+impl Checkpoint {
+    /// Starts a new checkpoint, which is saved to `path` at most once every
+    /// `interval`.
+    #[must_use]
+    pub fn new(
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        options: &Options,
+    ) -> Self {
+        let state = State {
+            saved: Instant::now(),
+            inodes: HashSet::new(),
+            finished: BTreeMap::new(),
+            current: None,
+        };
+
+        Self {
+            path: path.into(),
+            interval,
+            options: fingerprint(options),
+            now: options.reference_time.unwrap_or_else(SystemTime::now),
+            top: options.top,
+            state: Mutex::new(state),
+            file: Mutex::new(()),
+        }
+    }
+
+    /// Resumes from the checkpoint saved to `path`, or starts a new one if
+    /// there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint can not be read, is invalid, or was
+    /// saved with other options.
+    pub fn resume(
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        options: &Options,
+    ) -> Result<Self> {
+        let mut checkpoint = Self::new(path, interval, options);
+
+        let content = match fs::read_to_string(&checkpoint.path) {
+            Ok(content) => content,
+
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                log::info!(
+                    "no checkpoint to resume, starting anew: {}",
+                    checkpoint.path.display()
+                );
+
+                return Ok(checkpoint);
+            }
+
+            Err(error) => {
+                return Err(error).with_context(|| {
+                    format!(
+                        "error reading checkpoint {}",
+                        checkpoint.path.display()
+                    )
+                });
+            }
+        };
+
+        checkpoint
+            .parse(&content, options.reference_time)
+            .with_context(|| {
+                format!("invalid checkpoint {}", checkpoint.path.display())
+            })?;
+
+        Ok(checkpoint)
+    }
+
+    /// Returns the point in time ages are measured against, which is the
+    /// same for the interrupted and the resumed run.
+    #[must_use]
+    pub const fn reference_time(&self) -> SystemTime {
+        self.now
+    }
+
+    /// Returns the hard links visited before, to share with all directories
+    /// of the run.
+    #[must_use]
+    pub fn hard_links(&self) -> HardLinks {
+        HardLinks {
+            inodes: Inodes(Mutex::new(self.lock().inodes.clone())),
+        }
+    }
+
+    /// Returns the data of the input directory, if it was finished before.
+    #[must_use]
+    pub fn finished(&self, dir: &str) -> Option<Data> {
+        self.lock().finished.get(dir).cloned()
+    }
+
+    /// Records the data of a finished input directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint is due and can not be saved.
+    pub fn finish(&self, dir: &str, data: &Data) -> Result<()> {
+        let mut state = self.lock();
+
+        state.finished.insert(dir.to_owned(), data.clone());
+        state.current = None;
+
+        self.save_if_due(state)
+    }
+
+    /// Saves the checkpoint now, e.g. after an input directory failed, so
+    /// that it can be resumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint can not be saved.
+    pub fn save(&self) -> Result<()> {
+        let state = self.lock();
+        let file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+
+        self.save_locked(state, file)
+    }
+
+    /// Removes the checkpoint file, e.g. after all input directories are
+    /// finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists and can not be removed.
+    pub fn remove(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error)
+                .with_context(|| {
+                    format!(
+                        "error removing checkpoint {}",
+                        self.path.display()
+                    )
+                }),
+
+            _ => Ok(()),
+        }
+    }
+
+    /// Continues the progress of the input directory, if it was saved, or
+    /// starts it anew. Returns the directories waiting to be read and the
+    /// directories already visited.
+    pub(super) fn start(&self, dir: &str) -> (Vec<PathBuf>, Vec<(u64, u64)>) {
+        let mut state = self.lock();
+
+        let progress = match &mut state.current {
+            Some(progress) if progress.dir == dir => {
+                log::info!("resuming {dir}");
+                progress
+            }
+
+            current => current.insert(Progress::new(dir)),
+        };
+
+        let pending = progress.pending.iter().cloned().collect();
+        let directories = progress.directories.iter().copied().collect();
+
+        drop(state);
+
+        (pending, directories)
+    }
+
+    /// Records a completely read directory, together with the directories
+    /// found in it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkpoint is due and can not be saved.
+    pub(super) fn complete(
+        &self,
+        dir: &Path,
+        breakdown: PathBuf,
+        data: Data,
+        found: &Found,
+    ) -> Result<()> {
+        let mut state = self.lock();
+
+        state.inodes.extend(&found.inodes);
+
+        if let Some(progress) = &mut state.current {
+            *progress.data.entry(breakdown).or_default() += data;
+
+            progress.pending.remove(dir);
+            progress
+                .pending
+                .extend(found.subdirectories.iter().cloned());
+            progress.directories.extend(&found.directories);
+        }
+
+        self.save_if_due(state)
+    }
+
+    /// Takes the data of the input directory in progress.
+    pub(super) fn take(&self) -> HashMap<PathBuf, Data> {
+        self.lock()
+            .current
+            .as_mut()
+            .map(|progress| std::mem::take(&mut progress.data))
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Saves the checkpoint if it is due. A save is skipped while another
+    /// one is still writing, so that an older snapshot never replaces a
+    /// newer one.
+    fn save_if_due(&self, state: MutexGuard<'_, State>) -> Result<()> {
+        if state.saved.elapsed() < self.interval {
+            return Ok(());
+        }
+
+        let Ok(file) = self.file.try_lock() else {
+            return Ok(());
+        };
+
+        self.save_locked(state, file)
+    }
+
+    /// Serializes the state while it is locked and writes the file after
+    /// releasing the lock, so that other threads are not held up.
+    fn save_locked(
+        &self,
+        mut state: MutexGuard<'_, State>,
+        file: MutexGuard<'_, ()>,
+    ) -> Result<()> {
+        let out = self.serialize(&state)?;
+        state.saved = Instant::now();
+        drop(state);
+
+        self.write(&out).with_context(|| {
+            format!("error saving checkpoint {}", self.path.display())
+        })?;
+
+        drop(file);
+
+        Ok(())
+    }
+
+    fn serialize(&self, state: &State) -> Result<String> {
+        let mut out = String::new();
+
+        writeln!(out, "{HEADER}")?;
+        writeln!(out, "options {}", escape(self.options.as_bytes()))?;
+        writeln!(out, "now {}", time(Some(self.now)))?;
+
+        for (dev, ino) in &state.inodes {
+            writeln!(out, "inode {dev} {ino}")?;
+        }
+
+        for (dir, data) in &state.finished {
+            write!(out, "finished {}", escape(dir.as_bytes()))?;
+            encode(data, &mut out)?;
+            writeln!(out)?;
+        }
+
+        if let Some(progress) = &state.current {
+            writeln!(out, "current {}", escape(progress.dir.as_bytes()))?;
+
+            for (dev, ino) in &progress.directories {
+                writeln!(out, "directory {dev} {ino}")?;
+            }
+
+            for path in &progress.pending {
+                writeln!(out, "pending {}", escape(&path_to_bytes(path)))?;
+            }
+
+            for (path, data) in &progress.data {
+                write!(out, "data {}", escape(&path_to_bytes(path)))?;
+                encode(data, &mut out)?;
+                writeln!(out)?;
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn write(&self, out: &str) -> Result<()> {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, out)?;
+        fs::rename(&temporary, &self.path)?;
+
+        log::debug!("saved checkpoint: {}", self.path.display());
+
+        Ok(())
+    }
+
+    fn parse(
+        &mut self,
+        content: &str,
+        reference_time: Option<SystemTime>,
+    ) -> Result<()> {
+        let mut lines = content.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("unknown format"));
+        }
+
+        let state =
+            self.state.get_mut().unwrap_or_else(PoisonError::into_inner);
+
+        for (i, line) in lines.enumerate() {
+            let mut tokens = Tokens(line.split(' '));
+
+            let record = tokens.next()?;
+
+            let result = match record {
+                "options" => {
+                    if tokens.string()? == self.options {
+                        Ok(())
+                    } else {
+                        Err(anyhow!("saved with other options"))
+                    }
+                }
+
+                "now" => {
+                    let now = tokens.time()?.unwrap_or(UNIX_EPOCH);
+
+                    if reference_time.is_some_and(|time| time != now) {
+                        Err(anyhow!("saved with another reference time"))
+                    } else {
+                        self.now = now;
+                        Ok(())
+                    }
+                }
+
+                "inode" => {
+                    state.inodes.insert((tokens.number()?, tokens.number()?));
+                    Ok(())
+                }
+
+                "finished" => {
+                    let dir = tokens.string()?;
+                    let data = decode(&mut tokens, self.top)?;
+                    state.finished.insert(dir, data);
+                    Ok(())
+                }
+
+                "current" => {
+                    let mut progress = Progress::new(&tokens.string()?);
+                    progress.pending.clear();
+                    state.current = Some(progress);
+                    Ok(())
+                }
+
+                "directory" | "pending" | "data" => {
+                    let progress = state
+                        .current
+                        .as_mut()
+                        .ok_or_else(|| anyhow!("{record} before current"))?;
+
+                    match record {
+                        "directory" => {
+                            progress
+                                .directories
+                                .insert((tokens.number()?, tokens.number()?));
+                        }
+
+                        "pending" => {
+                            progress.pending.insert(tokens.path()?);
+                        }
+
+                        _ => {
+                            let path = tokens.path()?;
+                            let data = decode(&mut tokens, self.top)?;
+                            progress.data.insert(path, data);
+                        }
+                    }
+
+                    Ok(())
+                }
+
+                _ => Err(anyhow!("unknown record: {record}")),
+            };
+
+            result
+                .and_then(|()| tokens.end())
+                .with_context(|| format!("line {}", i + 2))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the options that affect the data, i.e. not those that only
+/// affect how or how fast it is gathered, nor the reference time, which is
+/// saved separately.
+///
+/// The version is part of it, to be increased whenever options are added or
+/// change their meaning, which rejects older checkpoints.
+fn fingerprint(options: &Options) -> String {
+    fn list<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+        let values: Vec<String> =
+            values.into_iter().map(|value| value.to_string()).collect();
+
+        format!("{values:?}")
+    }
+
+    fn optional(value: Option<impl ToString>) -> String {
+        value.map_or_else(|| String::from("-"), |value| value.to_string())
+    }
+
+    fn patterns(patterns: &[Pattern]) -> String {
+        list(patterns.iter().map(|pattern| {
+            let prefix = if pattern.is_path() { "/" } else { "" };
+            format!("{prefix}{}", pattern.glob())
+        }))
+    }
+
+    let symlinks = match options.symlinks {
+        Symlinks::Skip => "skip",
+        Symlinks::Follow => "all",
+        Symlinks::FollowWithinRoot => "root",
+    };
+
+    let fields = [
+        ("ages", list(&options.ages_in_days)),
+        ("buckets", options.buckets.to_string()),
+        ("times", list(options.times.iter().map(|time| time.name()))),
+        ("exclude", patterns(options.filter.excludes())),
+        ("include", patterns(options.filter.includes())),
+        ("depth", optional(options.depth)),
+        ("min_depth", optional(options.min_depth)),
+        ("max_depth", optional(options.max_depth)),
+        ("min_size", optional(options.min_size)),
+        ("max_size", optional(options.max_size)),
+        ("by_user", options.by_user.to_string()),
+        ("by_group", options.by_group.to_string()),
+        ("by_extension", options.by_extension.to_string()),
+        ("by_device", options.by_device.to_string()),
+        ("top_extensions", optional(options.top_extensions)),
+        ("size_buckets", list(&options.size_buckets)),
+        ("top", optional(options.top)),
+        ("one_file_system", options.one_file_system.to_string()),
+        ("exclude_fs_types", list(&options.exclude_fs_types)),
+        (
+            "exclude_mounts",
+            list(options.exclude_mounts.iter().map(|path| path.display())),
+        ),
+        ("symlinks", symlinks.to_owned()),
+    ];
+
+    let mut fingerprint = String::from("version=1");
+
+    for (name, value) in fields {
+        // writing to a string can not fail
+        let _ = write!(fingerprint, " {name}={value}");
+    }
+
+    fingerprint
+}
+
+/// Appends data as space-separated tokens, each preceded by a space.
+fn encode(data: &Data, out: &mut String) -> std::fmt::Result {
+    let total = data.get_total();
+    let skipped = data.get_skipped();
+
+    write!(
+        out,
+        " {} {} {} {} {} {} {} {} {} {} {}",
+        total.bytes,
+        total.allocated,
+        total.files,
+        data.get_hard_links(),
+        data.get_followed_symlinks(),
+        data.get_skipped_symlinks(),
+        skipped.permission_denied,
+        skipped.other_file_system,
+        skipped.special_files,
+        u8::from(data.has_buckets()),
+        data.get_atime_mode().map_or("-", AtimeMode::name),
+    )?;
+
+    let ages = data.get_ages();
+    write!(out, " {}", ages.len())?;
+
+    for age in ages {
+        write!(out, " {age}")?;
+
+        for time in Time::ALL {
+            let amount = data.get(*age, time).unwrap_or_default();
+            write!(
+                out,
+                " {} {} {}",
+                amount.bytes, amount.allocated, amount.files
+            )?;
+        }
+    }
+
+    let errors = data.get_errors();
+    write!(out, " {}", errors.len())?;
+
+    for (kind, count) in errors {
+        write!(out, " {} {count}", escape(kind.as_bytes()))?;
+    }
+
+    let top = data.get_top();
+    write!(out, " {}", top.len())?;
+
+    for (age, files) in top {
+        write!(out, " {age} {}", files.len())?;
+
+        for file in files {
+            write!(
+                out,
+                " {} {} {} {}",
+                file.bytes,
+                time(file.accessed),
+                time(file.modified),
+                escape(&path_to_bytes(&file.path))
+            )?;
+        }
+    }
+
+    if let Some(breakdown) = data.get_breakdown() {
+        write!(out, " {}", breakdown.len())?;
+
+        for (key, data) in breakdown {
+            match key {
+                Key::User(uid) => write!(out, " user {uid}")?,
+                Key::Group(gid) => write!(out, " group {gid}")?,
+                Key::Extension(extension) => {
+                    write!(
+                        out,
+                        " extension {}",
+                        escape(extension.as_bytes())
+                    )?;
+                }
+                Key::OtherExtensions => write!(out, " other")?,
+                Key::Size { min, max } => {
+                    let max = max.map_or_else(
+                        || String::from("-"),
+                        |max| max.to_string(),
+                    );
+                    write!(out, " size {min} {max}")?;
+                }
+                Key::Device(device) => write!(out, " device {device}")?,
+            }
+
+            encode(data, out)?;
+        }
+    } else {
+        write!(out, " -")?;
+    }
+
+    if let Some(subdirectories) = data.get_subdirectories() {
+        write!(out, " {}", subdirectories.len())?;
+
+        for (path, data) in subdirectories {
+            write!(out, " {}", escape(&path_to_bytes(path)))?;
+            encode(data, out)?;
+        }
+    } else {
+        write!(out, " -")?;
+    }
+
+    Ok(())
+}
+
+/// Reads data written by [`encode`].
+fn decode(tokens: &mut Tokens, top: Option<usize>) -> Result<Data> {
+    let total = tokens.amount()?;
+
+    let mut data = Data::default()
+        .with_total(total)
+        .with_hard_links(tokens.number()?)
+        .with_followed_symlinks(tokens.number()?)
+        .with_skipped_symlinks(tokens.number()?)
+        .with_skipped(Skipped {
+            permission_denied: tokens.number()?,
+            other_file_system: tokens.number()?,
+            special_files: tokens.number()?,
+        })
+        .with_buckets(tokens.number::<u8>()? == 1)
+        .with_atime_mode(tokens.optional()?);
+
+    for _ in 0..tokens.number::<usize>()? {
+        let age = tokens.number()?;
+
+        data = data.with_ages(&[age]);
+
+        for time in Time::ALL {
+            data.insert(age, time, tokens.amount()?);
+        }
+    }
+
+    for _ in 0..tokens.number::<usize>()? {
+        let kind = tokens.string()?;
+        data.add_errors(kind, tokens.number()?);
+    }
+
+    for _ in 0..tokens.number::<usize>()? {
+        let age = tokens.number()?;
+        let files = tokens.number::<usize>()?;
+
+        for _ in 0..files {
+            let file = TopFile {
+                bytes: tokens.number()?,
+                accessed: tokens.time()?,
+                modified: tokens.time()?,
+                path: tokens.path()?,
+            };
+
+            data.add_top(age, top.unwrap_or(files), file);
+        }
+    }
+
+    if let Some(keys) = tokens.optional::<usize>()? {
+        data = data.with_breakdown();
+
+        for _ in 0..keys {
+            let key = match tokens.next()? {
+                "user" => Key::User(tokens.number()?),
+                "group" => Key::Group(tokens.number()?),
+                "extension" => Key::Extension(tokens.string()?),
+                "other" => Key::OtherExtensions,
+                "size" => Key::Size {
+                    min: tokens.number()?,
+                    max: tokens.optional()?,
+                },
+                "device" => Key::Device(tokens.number()?),
+                key => return Err(anyhow!("unknown key: {key}")),
+            };
+
+            data.add_breakdown(key, decode(tokens, top)?);
+        }
+    }
+
+    if let Some(paths) = tokens.optional::<usize>()? {
+        let mut subdirectories = BTreeMap::new();
+
+        for _ in 0..paths {
+            let path = tokens.path()?;
+            subdirectories.insert(path, decode(tokens, top)?);
+        }
+
+        data = data.with_subdirectories(subdirectories);
+    }
+
+    Ok(data)
+}
+
+/// Space-separated tokens of a line.
+struct Tokens<'a>(Split<'a, char>);
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Result<&'a str> {
+        self.0
+            .next()
+            .ok_or_else(|| anyhow!("unexpected end of line"))
+    }
+
+    fn end(&mut self) -> Result<()> {
+        self.0
+            .next()
+            .map_or(Ok(()), |token| Err(anyhow!("unexpected token: {token}")))
+    }
+
+    fn number<T: FromStr>(&mut self) -> Result<T> {
+        let token = self.next()?;
+
+        token
+            .parse()
+            .map_err(|_| anyhow!("invalid number: {token}"))
+    }
+
+    /// Returns a value, or `None` if it is `-`.
+    fn optional<T: FromStr>(&mut self) -> Result<Option<T>> {
+        match self.next()? {
+            "-" => Ok(None),
+            token => token
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow!("invalid value: {token}")),
+        }
+    }
+
+    fn amount(&mut self) -> Result<Amount> {
+        Ok(Amount {
+            bytes: self.number()?,
+            allocated: self.number()?,
+            files: self.number()?,
+        })
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = unescape(self.next()?)?;
+        String::from_utf8(bytes).map_err(|_| anyhow!("invalid UTF-8"))
+    }
+
+    fn path(&mut self) -> Result<PathBuf> {
+        Ok(path_from_bytes(unescape(self.next()?)?))
+    }
+
+    fn time(&mut self) -> Result<Option<SystemTime>> {
+        let token = self.next()?;
+
+        if token == "-" {
+            return Ok(None);
+        }
+
+        let (before, seconds) = token
+            .strip_prefix('-')
+            .map_or((false, token), |seconds| (true, seconds));
+
+        let duration = seconds
+            .split_once('.')
+            .and_then(|(seconds, nanos)| {
+                Some(Duration::new(seconds.parse().ok()?, nanos.parse().ok()?))
+            })
+            .ok_or_else(|| anyhow!("invalid time: {token}"))?;
+
+        let time = if before {
+            UNIX_EPOCH.checked_sub(duration)
+        } else {
+            UNIX_EPOCH.checked_add(duration)
+        };
+
+        time.map(Some)
+            .ok_or_else(|| anyhow!("invalid time: {token}"))
+    }
+}
+
+/// Formats a point in time as seconds and nanoseconds since the Unix epoch,
+/// or `-` if there is none.
+fn time(time: Option<SystemTime>) -> String {
+    let Some(time) = time else {
+        return String::from("-");
+    };
+
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => {
+            format!("{}.{:09}", duration.as_secs(), duration.subsec_nanos())
+        }
+
+        Err(error) => {
+            let duration = error.duration();
+            format!("-{}.{:09}", duration.as_secs(), duration.subsec_nanos())
+        }
+    }
+}
+
+/// Escapes all bytes but printable ASCII characters as `%XX`, so that tokens
+/// contain neither spaces nor line breaks. The empty string is escaped as a
+/// single `%`.
+fn escape(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return String::from("%");
+    }
+
+    let mut escaped = String::with_capacity(bytes.len());
+
+    for byte in bytes {
+        if byte.is_ascii_graphic() && *byte != b'%' {
+            escaped.push(char::from(*byte));
+        } else {
+            // writing to a string can not fail
+            let _ = write!(escaped, "%{byte:02X}");
+        }
+    }
+
+    escaped
+}
+
+/// Reverses [`escape`].
+fn unescape(token: &str) -> Result<Vec<u8>> {
+    if token == "%" {
+        return Ok(vec![]);
+    }
+
+    let mut bytes = Vec::with_capacity(token.len());
+    let mut rest = token.as_bytes();
+
+    while let Some((byte, tail)) = rest.split_first() {
+        if *byte == b'%' {
+            let byte = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow!("invalid escape: {token}"))?;
+
+            bytes.push(byte);
+            rest = &tail[2..];
+        } else {
+            bytes.push(*byte);
+            rest = tail;
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(target_family = "unix")]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(target_family = "unix"))]
+fn path_to_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(target_family = "unix")]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(target_family = "unix"))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::analysis::universal::run;
+
+    fn options() -> Options {
+        Options {
+            ages_in_days: vec![1],
+            times: vec![Time::Accessed, Time::Modified],
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        let file = TopFile {
+            path: PathBuf::from("a b/%c\n"),
+            bytes: 4,
+            accessed: Some(UNIX_EPOCH - Duration::new(1, 5)),
+            modified: None,
+        };
+
+        let mut extension = Data::default().with_total(Amount {
+            bytes: 4,
+            allocated: 8,
+            files: 1,
+        });
+        extension.add_errors(String::from("not found"), 2);
+        extension.add_top(1, 3, file);
+
+        let mut data = Data::default()
+            .with_ages(&[1, 30])
+            .with_buckets(true)
+            .with_atime_mode(Some(AtimeMode::Relatime))
+            .with_hard_links(1)
+            .with_skipped(Skipped {
+                permission_denied: 1,
+                other_file_system: 2,
+                special_files: 3,
+            });
+        data.insert(30, Time::Created, extension.get_total());
+        data.add_breakdown(Key::Extension(String::new()), extension.clone());
+        data.add_breakdown(Key::Size { min: 0, max: None }, extension.clone());
+        data.add_breakdown(Key::Device(2049), extension);
+
+        let data = Data::default().with_subdirectories(BTreeMap::from([
+            (PathBuf::new(), data.clone()),
+            (PathBuf::from("x"), data),
+        ]));
+
+        let mut encoded = String::new();
+        encode(&data, &mut encoded).unwrap();
+
+        let mut tokens = Tokens(encoded[1..].split(' '));
+        let decoded = decode(&mut tokens, Some(3)).unwrap();
+        tokens.end().unwrap();
+
+        let mut reencoded = String::new();
+        encode(&decoded, &mut reencoded).unwrap();
+
+        assert_eq!(encoded, reencoded);
+    }
+
+    #[test]
+    fn fingerprinting() -> Result<()> {
+        let options = Options {
+            filter: crate::Filter::default()
+                .with_exclude(".snapshots")?
+                .with_include("/data/*")?,
+            min_size: Some(1024),
+            threads: 4,
+            ..options()
+        };
+
+        assert_eq!(
+            fingerprint(&options),
+            "version=1 ages=[\"1\"] buckets=false \
+             times=[\"accessed\", \"modified\"] exclude=[\".snapshots\"] \
+             include=[\"/data/*\"] depth=- min_depth=- max_depth=- \
+             min_size=1024 max_size=- by_user=false by_group=false \
+             by_extension=false by_device=false top_extensions=- \
+             size_buckets=[] top=- one_file_system=false \
+             exclude_fs_types=[] exclude_mounts=[] symlinks=skip"
+        );
+
+        // options that only affect how the data is gathered are left out
+        let other = Options {
+            threads: 1,
+            strict: true,
+            max_stats_per_second: Some(10),
+            reference_time: Some(UNIX_EPOCH),
+            ..options.clone()
+        };
+
+        assert_eq!(fingerprint(&other), fingerprint(&options));
+
+        Ok(())
+    }
+
+    #[test]
+    fn escaping() {
+        for s in ["", "%", "a b", "ä\n%41"] {
+            let escaped = escape(s.as_bytes());
+
+            assert!(!escaped.contains([' ', '\n']));
+            assert_eq!(unescape(&escaped).unwrap(), s.as_bytes());
+        }
+
+        assert!(unescape("%4").is_err());
+        assert!(unescape("%+1").is_err());
+    }
+
+    #[test]
+    fn resume() -> Result<()> {
+        let tree = tempfile::tempdir()?;
+        let work = tempfile::tempdir()?;
+
+        fs::create_dir(tree.path().join("x"))?;
+        fs::create_dir(tree.path().join("y"))?;
+        fs::write(tree.path().join("x/a"), "a")?;
+        fs::write(tree.path().join("y/b"), "bb")?;
+
+        let root = tree.path().to_str().unwrap();
+        let file = work.path().join("checkpoint");
+        let options = options();
+
+        // the input directory is read, of its subdirectories `y` is finished
+        let checkpoint = Checkpoint::new(&file, Duration::ZERO, &options);
+        checkpoint.start(root);
+
+        let found = Found {
+            subdirectories: vec![tree.path().join("x")],
+            ..Found::default()
+        };
+
+        checkpoint.complete(
+            tree.path(),
+            PathBuf::new(),
+            options.data(),
+            &found,
+        )?;
+
+        let resumed = Checkpoint::resume(&file, Duration::ZERO, &options)?;
+        assert_eq!(resumed.reference_time(), checkpoint.reference_time());
+
+        let data = run(root, &options, &resumed.hard_links(), Some(&resumed))?;
+        assert_eq!(data.get_total().bytes, 1);
+
+        let other = Options {
+            ages_in_days: vec![2],
+            ..options
+        };
+
+        assert!(Checkpoint::resume(&file, Duration::ZERO, &other).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs::{self, ReadDir};
+use std::io;
+use std::path::Path;
+
+/// Opens a directory for reading, even if its path is longer than
+/// `PATH_MAX`.
+///
+/// Such a path is opened in chunks shorter than `PATH_MAX`, each relative to
+/// the handle of the previous one, and the directory is then read via its
+/// handle in `/proc/self/fd`. Entries must thus be joined to `dir` by their
+/// file name, not by [`fs::DirEntry::path`].
+#[cfg(target_os = "linux")]
+pub fn read_dir(dir: &Path) -> io::Result<ReadDir> {
+    use std::os::fd::AsRawFd;
+
+    use rustix::io::Errno;
+
+    match fs::read_dir(dir) {
+        Err(error)
+            if error.raw_os_error()
+                == Some(Errno::NAMETOOLONG.raw_os_error()) =>
+        {
+            let fd = open(dir)?;
+            fs::read_dir(format!("/proc/self/fd/{}", fd.as_raw_fd()))
+        }
+
+        result => result,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn read_dir(dir: &Path) -> io::Result<ReadDir> {
+    fs::read_dir(dir)
+}
+
+/// Maximum length in bytes of each chunk opened at once, well below
+/// `PATH_MAX` of 4096 bytes.
+#[cfg(target_os = "linux")]
+const CHUNK: usize = 2048;
+
+#[cfg(target_os = "linux")]
+fn open(dir: &Path) -> io::Result<std::os::fd::OwnedFd> {
+    use std::os::fd::{AsFd, OwnedFd};
+    use std::path::PathBuf;
+
+    use rustix::fs::{CWD, Mode, OFlags, openat};
+
+    let flags = OFlags::RDONLY | OFlags::DIRECTORY | OFlags::CLOEXEC;
+
+    let mut fd: Option<OwnedFd> = None;
+    let mut chunk = PathBuf::new();
+
+    for component in dir.components() {
+        let len = component.as_os_str().len();
+
+        if !chunk.as_os_str().is_empty()
+            && chunk.as_os_str().len() + len >= CHUNK
+        {
+            let parent = fd.as_ref().map_or(CWD, AsFd::as_fd);
+            fd = Some(openat(parent, &chunk, flags, Mode::empty())?);
+            chunk.clear();
+        }
+
+        chunk.push(component);
+    }
+
+    let parent = fd.as_ref().map_or(CWD, AsFd::as_fd);
+    Ok(openat(parent, &chunk, flags, Mode::empty())?)
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use anyhow::{Error, Result, anyhow};
+
+/// Directories waiting to be traversed, shared by all worker threads.
+///
+/// Directories are taken last in, first out, so traversal is depth-first and
+/// the queue stays small even for wide trees.
+pub struct Queue {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+struct State {
+    dirs: Vec<PathBuf>,
+
+    /// Number of directories currently being traversed.
+    active: usize,
+
+    /// First error of any worker, which stops all other workers.
+    error: Option<Error>,
+}
+
+impl Queue {
+    pub const fn new(dirs: Vec<PathBuf>) -> Self {
+        let state = State {
+            dirs,
+            active: 0,
+            error: None,
+        };
+
+        Self {
+            state: Mutex::new(state),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Returns the next directory to traverse.
+    ///
+    /// Blocks while the queue is empty but other workers may still add
+    /// directories. Returns `None` if traversal is finished or has failed.
+    pub fn pop(&self) -> Option<Task<'_>> {
+        let mut state = self.lock();
+
+        loop {
+            if state.error.is_some() {
+                return None;
+            }
+
+            if let Some(dir) = state.dirs.pop() {
+                state.active += 1;
+
+                return Some(Task {
+                    queue: self,
+                    dir,
+                    done: false,
+                });
+            }
+
+            if state.active == 0 {
+                return None;
+            }
+
+            state = self
+                .condvar
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Adds a directory to traverse.
+    pub fn push(&self, dir: PathBuf) {
+        self.lock().dirs.push(dir);
+        self.condvar.notify_one();
+    }
+
+    /// Marks a directory returned by [`Queue::pop`] as traversed.
+    fn done(&self, result: Result<()>) {
+        let mut state = self.lock();
+
+        state.active -= 1;
+
+        if let Err(error) = result {
+            state.error.get_or_insert(error);
+        }
+
+        let finished = state.active == 0 && state.dirs.is_empty();
+
+        if finished || state.error.is_some() {
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Returns the first error any worker encountered.
+    pub fn into_result(self) -> Result<()> {
+        let state = self
+            .state
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        state.error.map_or(Ok(()), Err)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A directory returned by [`Queue::pop`].
+///
+/// If it is dropped without [`Task::done`], e.g. because its worker panicked,
+/// traversal fails, instead of the other workers waiting for it forever.
+pub struct Task<'a> {
+    queue: &'a Queue,
+    dir: PathBuf,
+    done: bool,
+}
+
+impl Task<'_> {
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Marks the directory as traversed.
+    pub fn done(mut self, result: Result<()>) {
+        self.done = true;
+        self.queue.done(result);
+    }
+}
+
+impl Drop for Task<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.queue.done(Err(anyhow!(
+                "traversal of {} was aborted",
+                self.dir.display()
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::panic;
+    use std::thread;
+
+    #[test]
+    fn panic() {
+        let queue = Queue::new(vec![PathBuf::from("a"), PathBuf::from("b")]);
+
+        let first = queue.pop().unwrap();
+        assert_eq!(first.dir(), Path::new("b"));
+
+        thread::scope(|scope| {
+            let worker = scope.spawn(|| {
+                let _task = queue.pop().unwrap();
+                panic!("worker panicked");
+            });
+
+            assert!(worker.join().is_err());
+        });
+
+        // the other worker finishes instead of waiting for the panicked one
+        first.done(Ok(()));
+        assert!(queue.pop().is_none());
+        assert!(queue.into_result().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Options;
+
+/// Longest delay the adaptive mode backs off to between `stat` calls.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Shortest delay the adaptive mode backs off with, which is doubled while
+/// the latency stays high.
+const MIN_DELAY: Duration = Duration::from_micros(100);
+
+/// Latency above this multiple of the baseline counts as rising.
+const LATENCY_FACTOR: u32 = 2;
+
+/// Baseline below which latency never counts as rising, because such short
+/// latencies do not indicate a loaded file system, and are easily inflated
+/// by the delay itself, e.g. by cold caches after sleeping.
+const LATENCY_FLOOR: Duration = Duration::from_millis(1);
+
+/// Weight of each latency in the moving average.
+const AVERAGE_WEIGHT: f64 = 0.1;
+
+/// Weight of the average when the baseline rises towards it, which is much
+/// slower than the average itself, so that a lasting rise of the latency
+/// becomes the new baseline instead of backing off forever.
+const BASELINE_WEIGHT: f64 = 0.02;
+
+/// Limits the rate of metadata requests of all worker threads together.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    stats: Option<Limiter>,
+    dirs: Option<Limiter>,
+    adaptive: Option<Adaptive>,
+}
+
+impl Throttle {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            stats: options.max_stats_per_second.map(Limiter::new),
+            dirs: options.max_dirs_per_second.map(Limiter::new),
+            adaptive: options.adaptive_throttle.then(Adaptive::default),
+        }
+    }
+
+    /// Waits until the next `stat` call is allowed, then runs it and records
+    /// its latency.
+    pub fn stat<T>(&self, request: impl FnOnce() -> T) -> T {
+        if let Some(limiter) = &self.stats {
+            limiter.wait();
+        }
+
+        let Some(adaptive) = &self.adaptive else {
+            return request();
+        };
+
+        adaptive.wait();
+
+        let start = Instant::now();
+        let result = request();
+        adaptive.record(start.elapsed());
+
+        result
+    }
+
+    /// Waits until the next directory may be read.
+    pub fn dir(&self) {
+        if let Some(limiter) = &self.dirs {
+            limiter.wait();
+        }
+    }
+}
+
+/// Spaces out events evenly to a maximum rate per second.
+#[derive(Debug)]
+struct Limiter {
+    interval: Duration,
+
+    /// Earliest time of the next event.
+    next: Mutex<Instant>,
+}
+
+impl Limiter {
+    fn new(per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn wait(&self) {
+        let mut next =
+            self.next.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let now = Instant::now();
+        let slot = (*next).max(now);
+        *next = slot + self.interval;
+
+        drop(next);
+
+        let delay = slot - now;
+
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+}
+
+/// Backs off when the latency of `stat` calls rises above its baseline,
+/// which follows the average latency down immediately, but up only slowly.
+#[derive(Debug, Default)]
+struct Adaptive {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Exponential moving average of the latency.
+    average: Option<Duration>,
+
+    /// Latency considered normal.
+    baseline: Option<Duration>,
+
+    /// Current delay before each `stat` call.
+    delay: Duration,
+}
+
+impl Adaptive {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait(&self) {
+        let delay = self.lock().delay;
+
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut state = self.lock();
+
+        let average = state.average.map_or(latency, |average| {
+            average.mul_f64(1.0 - AVERAGE_WEIGHT)
+                + latency.mul_f64(AVERAGE_WEIGHT)
+        });
+
+        let baseline = state.baseline.map_or(average, |baseline| {
+            if average < baseline {
+                average
+            } else {
+                baseline.mul_f64(1.0 - BASELINE_WEIGHT)
+                    + average.mul_f64(BASELINE_WEIGHT)
+            }
+        });
+
+        state.delay = if average > baseline.max(LATENCY_FLOOR) * LATENCY_FACTOR
+        {
+            (state.delay * 2).clamp(MIN_DELAY, MAX_DELAY)
+        } else {
+            Duration::ZERO
+        };
+
+        state.average = Some(average);
+        state.baseline = Some(baseline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adaptive() {
+        let adaptive = Adaptive::default();
+
+        adaptive.record(Duration::from_millis(1));
+        assert_eq!(adaptive.lock().delay, Duration::ZERO);
+
+        for _ in 0..20 {
+            adaptive.record(Duration::from_millis(10));
+        }
+
+        let delay = adaptive.lock().delay;
+        assert!(delay > Duration::ZERO);
+
+        for _ in 0..100 {
+            adaptive.record(Duration::from_millis(1));
+        }
+
+        assert_eq!(adaptive.lock().delay, Duration::ZERO);
+    }
+
+    #[test]
+    fn adaptive_lasting_rise() {
+        let adaptive = Adaptive::default();
+
+        adaptive.record(Duration::from_millis(1));
+
+        let delays: Vec<Duration> = (0..200)
+            .map(|_| {
+                adaptive.record(Duration::from_millis(10));
+                adaptive.lock().delay
+            })
+            .collect();
+
+        // backs off at first, but the higher latency becomes the baseline
+        assert!(delays[..50].iter().any(|delay| !delay.is_zero()));
+        assert!(delays[150..].iter().all(Duration::is_zero));
+        assert!(adaptive.lock().baseline > Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    fn adaptive_floor() {
+        let adaptive = Adaptive::default();
+
+        adaptive.record(Duration::from_micros(2));
+
+        for _ in 0..100 {
+            adaptive.record(Duration::from_micros(500));
+            assert_eq!(adaptive.lock().delay, Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn adaptive_reset() {
+        let adaptive = Adaptive::default();
+
+        for latency in [1, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10] {
+            adaptive.record(Duration::from_millis(latency));
+        }
+
+        assert!(adaptive.lock().delay > MIN_DELAY);
+
+        // the delay is reset at once when the average is back to normal
+        let mut delays = vec![];
+
+        while !adaptive.lock().delay.is_zero() {
+            delays.push(adaptive.lock().delay);
+            adaptive.record(Duration::from_millis(1));
+        }
+
+        assert!(delays.len() < 20);
+        assert!(delays.iter().all(|delay| *delay > MIN_DELAY));
+    }
+}
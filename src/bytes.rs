@@ -0,0 +1,95 @@
+use anyhow::{Result, anyhow};
+
+const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+
+/// Parses a size in bytes with an optional unit, e.g. `4KiB` or `1MB`.
+///
+/// Units are case-insensitive. `K`, `KiB`, `M`, `MiB` etc. are binary, i.e.
+/// powers of 1024, `KB`, `MB` etc. are decimal, i.e. powers of 1000.
+///
+/// # Errors
+///
+/// Returns an error if the number or the unit is invalid.
+pub fn parse_bytes(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+
+    let (number, unit) = s.split_at(split);
+    let unit = unit.trim().to_uppercase();
+
+    let number: f64 =
+        number.parse().map_err(|_| anyhow!("invalid size: {s}"))?;
+
+    let factor = match unit.as_str() {
+        "" | "B" => 1,
+        unit => factor(unit).ok_or_else(|| anyhow!("invalid unit: {s}"))?,
+    };
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    let bytes = (number * factor as f64).round() as u64;
+
+    Ok(bytes)
+}
+
+/// Returns the factor of an uppercase unit other than bytes.
+fn factor(unit: &str) -> Option<u64> {
+    UNITS.iter().zip(1..).find_map(|(prefix, exponent)| {
+        let base: u64 = if unit == *prefix || unit == format!("{prefix}IB") {
+            1024
+        } else if unit == format!("{prefix}B") {
+            1000
+        } else {
+            return None;
+        };
+
+        Some(base.pow(exponent))
+    })
+}
+
+/// Formats a size in bytes with the largest binary unit it is a multiple
+/// of, e.g. `4KiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    let mut value = bytes;
+    let mut unit = None;
+
+    for u in UNITS {
+        if value == 0 || value % 1024 != 0 {
+            break;
+        }
+
+        value /= 1024;
+        unit = Some(u);
+    }
+
+    unit.map_or_else(|| format!("{value}B"), |unit| format!("{value}{unit}iB"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_bytes("512").unwrap(), 512);
+        assert_eq!(parse_bytes("4KiB").unwrap(), 4096);
+        assert_eq!(parse_bytes("4k").unwrap(), 4096);
+        assert_eq!(parse_bytes("1.5 GiB").unwrap(), 3 << 29);
+        assert_eq!(parse_bytes("1MB").unwrap(), 1_000_000);
+        assert!(parse_bytes("1XB").is_err());
+        assert!(parse_bytes("KiB").is_err());
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_bytes(0), "0B");
+        assert_eq!(format_bytes(1000), "1000B");
+        assert_eq!(format_bytes(4096), "4KiB");
+        assert_eq!(format_bytes(1 << 30), "1GiB");
+    }
+}
@@ -2,7 +2,7 @@ use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
-use clap::builder::{EnumValueParser, PossibleValue};
+use clap::builder::{EnumValueParser, PossibleValue, PossibleValuesParser};
 use clap::value_parser;
 use clap::{Arg, ArgAction, Command, ValueEnum};
 use clap::{crate_description, crate_name, crate_version};
@@ -12,29 +12,6 @@ use clap::{crate_description, crate_name, crate_version};
 // ----------------------------------------------------------------------------
 
 pub fn build(stdin_terminal: bool) -> Command {
-    let age = Arg::new("age")
-        .help("threshold in days")
-        .long_help("Specify thresholds in days.")
-        .action(ArgAction::Append)
-        .required(true)
-        .value_parser(value_parser!(u64));
-
-    let debug = Arg::new("debug")
-        .long("debug")
-        .long_help(
-"Adds very verbose output useful for debugging. Implies `--progress`."
-        )
-        .action(ArgAction::SetTrue)
-        .hide_short_help(true);
-
-    let progress = Arg::new("progress")
-        .long("progress")
-        .hide_short_help(true)
-        .action(ArgAction::SetTrue)
-        .help("show progress messages")
-        .long_help("Show progress message for each directory.")
-        .display_order(3);
-
     let dir = Arg::new("dir")
         .help("input directories")
         .long_help(
@@ -47,28 +24,6 @@ pub fn build(stdin_terminal: bool) -> Command {
         .last(true)
         .value_parser(is_dir);
 
-    let format = Arg::new("format")
-        .long("format")
-        .help("output format")
-        .long_help(
-"Specify output format of the report. `prometheus` uses the Prometheus \
- metric exposition format. `oneline` is intended as machine-readable output \
- that shows a colon (\":\") separated list of age, total, accessed, and \
- modified size in bytes, total, accessed, and modified number of files, \
- followed by the directory. `table` (cargo feature, enabled by default) shows \
- a pretty-printed table."
-        )
-        .action(ArgAction::Set)
-        .ignore_case(true)
-        .value_parser(EnumValueParser::<Output>::new())
-        .display_order(1);
-
-    let format = if cfg!(feature = "table") {
-        format.default_value("table")
-    } else {
-        format.required(true)
-    };
-
     let help = Arg::new("help")
         .short('?')
         .long("help")
@@ -85,11 +40,14 @@ pub fn build(stdin_terminal: bool) -> Command {
     Command::new(crate_name!())
         .version(crate_version!())
         .about(crate_description!())
-        .arg(age)
+        .args(general_args())
+        .args(report_args())
+        .args(breakdown_args())
+        .args(progress_args())
+        .args(filter_args())
+        .args(limit_args())
+        .args(performance_args())
         .arg(dir)
-        .arg(debug)
-        .arg(format)
-        .arg(progress)
         .args(conditional_compilation_args())
         .disable_help_flag(true)
         .disable_version_flag(true)
@@ -97,7 +55,497 @@ pub fn build(stdin_terminal: bool) -> Command {
         .arg(version)
 }
 
+fn general_args() -> Vec<Arg> {
+    let format = Arg::new("format")
+        .long("format")
+        .help("output format")
+        .long_help(
+"Specify output format of the report. `prometheus` uses the Prometheus \
+ metric exposition format. `oneline` is intended as machine-readable output \
+ that shows a colon (\":\") separated list of age or, with `--buckets`, \
+ age range, total size in bytes \
+ followed by the size for each time dimension (see `--time`) for each size \
+ dimension (see `--size`), total number of files followed by the number of \
+ files for each time dimension, with `--counts` the counts of links \
+ and skipped entries, with `--by-user` the user, with `--by-group` the group, \
+ with `--by-extension` the extension, with `--size-buckets` the size bucket, \
+ with `--by-device` the mount point, each of which is empty in rows of the \
+ other breakdowns, with `--depth` the depth, and finally the directory. Lines \
+ starting with `#` are not such rows but separate records, e.g. settings \
+ limiting which files are counted, like `--min-size`, precede the rows as \
+ lines of the form `#setting:name:value`. `table` (cargo feature, enabled by \
+ default) shows a pretty-printed table."
+        )
+        .action(ArgAction::Set)
+        .ignore_case(true)
+        .value_parser(EnumValueParser::<Output>::new())
+        .display_order(1);
+
+    let format = if cfg!(feature = "table") {
+        format.default_value("table")
+    } else {
+        format.required(true)
+    };
+
+    vec![
+        Arg::new("age")
+            .help("threshold in days")
+            .long_help("Specify thresholds in days.")
+            .action(ArgAction::Append)
+            .required(true)
+            .value_parser(value_parser!(u64)),
+
+        Arg::new("dedupe-inputs")
+            .long("dedupe-inputs")
+            .action(ArgAction::SetTrue)
+            .help("scan nested input directories only once")
+            .long_help(
+"Skip input directories that are inside another input directory, or the \
+ same as an earlier one, e.g. from a `find` pipeline. Their files are counted \
+ as part of the outermost input directory containing them, instead of being \
+ scanned twice. Without this flag, such input directories are scanned \
+ anyway, with a warning."
+            )
+            .display_order(1),
+
+        format,
+
+        Arg::new("reference-time")
+            .long("reference-time")
+            .help("measure ages against this time")
+            .long_help(
+"Measure the ages of all files against this point in time instead of the \
+ time the scan starts, which makes reports comparable and reproducible. The \
+ time is given in RFC 3339 format, e.g. `2024-01-31T12:00:00Z` or \
+ `2024-01-31T13:00:00+01:00`, or in seconds since the Unix epoch, e.g. \
+ `1706702400`. With `--spectrum-scale`, the time is passed to the policy in \
+ the local time zone, in which the policy evaluates file times."
+            )
+            .action(ArgAction::Set)
+            .value_name("time")
+            .display_order(1),
+
+        Arg::new("strict")
+            .long("strict")
+            .action(ArgAction::SetTrue)
+            .help("fail on errors of single files")
+            .long_help(
+"Skip the whole input directory on the first error of a single file or \
+ directory, e.g. one that vanished during the scan. By default, such errors \
+ are counted and reported per input directory instead, which is what you \
+ want on busy file systems. Does not apply to `--spectrum-scale`."
+            )
+            .display_order(1),
+    ]
+}
+
+fn report_args() -> Vec<Arg> {
+    vec![
+        Arg::new("buckets")
+            .long("buckets")
+            .action(ArgAction::SetTrue)
+            .help("report disjoint age ranges")
+            .long_help(
+"Report files in disjoint age ranges instead of below each threshold, i.e. \
+ younger than the smallest threshold, between adjacent thresholds, and at \
+ least as old as the largest threshold, e.g. ages `30 90` report the ranges \
+ `0-30`, `30-90` and `90+`. The cumulative numbers are the sums of the \
+ ranges below a threshold."
+            )
+            .display_order(1),
+
+        Arg::new("counts")
+            .long("counts")
+            .action(ArgAction::SetTrue)
+            .help("add link and skip counts to oneline and table rows")
+            .long_help(
+"Add the number of additional hard links, the number of followed and skipped \
+ symbolic links, the number of directories skipped as permission denied, of \
+ entries skipped on other file systems and of skipped special files to each \
+ row of `oneline` output, after the file counts, and as columns to the \
+ `table` output. `prometheus` output always reports them."
+            )
+            .display_order(1),
+
+        Arg::new("size")
+            .long("size")
+            .help("size dimension to report")
+            .long_help(
+"Specify which size of the files to report. `apparent` is the length of the \
+ files. `allocated` is the space the files actually occupy on the storage, \
+ which differs from the apparent size e.g. for sparse or compressed files and \
+ due to block size overhead. `both` reports both dimensions next to each \
+ other."
+            )
+            .action(ArgAction::Set)
+            .ignore_case(true)
+            .value_parser(PossibleValuesParser::new([
+                "apparent",
+                "allocated",
+                "both",
+            ]))
+            .default_value("apparent")
+            .display_order(5),
+
+        Arg::new("time")
+            .long("time")
+            .help("time dimensions to report")
+            .long_help(
+"Specify which time dimensions of the files to report. `accessed` is the \
+ access time, `modified` the modification time, and `changed` the status \
+ change time, which is also updated e.g. by restores or `chmod`. `created` is \
+ the birth time, which is not supported by all file systems. Files without a \
+ birth time are never counted as created. Multiple dimensions can be given \
+ as a comma-separated list."
+            )
+            .action(ArgAction::Append)
+            .ignore_case(true)
+            .value_delimiter(',')
+            .value_parser(PossibleValuesParser::new([
+                "accessed",
+                "modified",
+                "changed",
+                "created",
+            ]))
+            .default_values(["accessed", "modified"])
+            .display_order(5),
+
+        Arg::new("top")
+            .long("top")
+            .help("list the largest files past each threshold")
+            .long_help(
+"List the N largest files of each input directory that are older than each \
+ threshold in all time dimensions (see `--time`), along with their size, \
+ access time and modification time. `table` shows them in a separate section \
+ after the table. `oneline` shows them as separate records of the form \
+ `#top:age:rank:bytes:accessed:modified:path`, with times in seconds since \
+ the Unix epoch. `prometheus` shows them as additional metrics. With \
+ `--spectrum-scale`, the times the policy lists in local time are converted \
+ using the local time zone, which should thus match that of the cluster."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .display_order(1),
+    ]
+}
+
+fn breakdown_args() -> Vec<Arg> {
+    vec![
+        Arg::new("by-extension")
+            .long("by-extension")
+            .action(ArgAction::SetTrue)
+            .help("report by file extension")
+            .long_help(
+"Report each directory by file extension instead of in total. Extensions are \
+ compared case-insensitively. Suffixes of compressed files are reported \
+ together with the preceding extension, e.g. `.fastq.gz`. Files without \
+ extension are reported as `none`."
+            )
+            .display_order(1),
+
+        Arg::new("depth")
+            .long("depth")
+            .help("report subdirectories down to depth")
+            .long_help(
+"Report every subdirectory down to the given depth separately, in addition \
+ to the input directory, which has depth 0. Like `du`, the numbers of each \
+ directory include those of its subdirectories. All directories are gathered \
+ in a single traversal."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .display_order(5),
+
+        Arg::new("size-buckets")
+            .long("size-buckets")
+            .help("report by file size")
+            .long_help(
+"Report each directory by file size instead of in total. The given sizes are \
+ the upper bounds of the buckets, the last bucket contains all larger files, \
+ e.g. `4KiB,1MiB,1GiB` reports files smaller than 4 KiB, smaller than 1 MiB, \
+ smaller than 1 GiB, and at least 1 GiB. Sizes may have binary units, e.g. \
+ `K` or `KiB`, or decimal units, e.g. `KB`. Multiple sizes can be given as a \
+ comma-separated list."
+            )
+            .action(ArgAction::Append)
+            .value_delimiter(',')
+            .value_name("size")
+            .display_order(1),
+
+        Arg::new("top-extensions")
+            .long("top-extensions")
+            .help("report only the largest extensions")
+            .long_help(
+"Report only the N extensions with the largest size separately for each \
+ directory, the remaining extensions are reported as `other`. Implies \
+ `--by-extension`."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .display_order(1),
+    ]
+}
+
+fn progress_args() -> Vec<Arg> {
+    vec![
+        Arg::new("debug")
+            .long("debug")
+            .long_help(
+"Adds very verbose output useful for debugging. Implies `--progress`."
+            )
+            .action(ArgAction::SetTrue)
+            .hide_short_help(true)
+            .display_order(5),
+
+        Arg::new("progress")
+            .long("progress")
+            .hide_short_help(true)
+            .action(ArgAction::SetTrue)
+            .help("show progress messages")
+            .long_help("Show progress message for each directory.")
+            .display_order(3),
+
+        Arg::new("checkpoint")
+            .long("checkpoint")
+            .help("save progress to resume later")
+            .long_help(
+"Periodically save the progress of the scan to a file, so that an \
+ interrupted scan can be continued with `--resume`, e.g. to work within the \
+ wall-time limits of batch schedulers. Input directories are saved once they \
+ are finished. The universal directory traversal also saves the progress \
+ within an input directory, i.e. the data of all directories read so far and \
+ the directories still to be read. The file is removed when all input \
+ directories are finished.",
+            )
+            .action(ArgAction::Set)
+            .value_name("FILE")
+            .display_order(3),
+
+        Arg::new("checkpoint-interval")
+            .long("checkpoint-interval")
+            .help("seconds between saving progress")
+            .long_help(
+"Save the progress at most once every this many seconds. Defaults to 60 \
+ seconds."
+            )
+            .action(ArgAction::Set)
+            .value_name("SECONDS")
+            .value_parser(value_parser!(u64))
+            .requires("checkpoint")
+            .display_order(3),
+
+        Arg::new("resume")
+            .long("resume")
+            .action(ArgAction::SetTrue)
+            .help("resume from checkpoint")
+            .long_help(
+"Continue the scan saved with `--checkpoint`, skipping finished input \
+ directories and subdirectories. The report is the same as without \
+ interruption, in particular ages are measured against the start of the \
+ interrupted scan. The other options must not change, otherwise the \
+ checkpoint is rejected. Starts anew if there is no checkpoint, so that the \
+ same command can be resubmitted until it finishes."
+            )
+            .requires("checkpoint")
+            .display_order(3),
+    ]
+}
+
+fn filter_args() -> Vec<Arg> {
+    vec![
+        Arg::new("exclude")
+            .long("exclude")
+            .help("skip files and directories matching pattern")
+            .long_help(
+"Skip files and directories matching a glob pattern. Excluded directories are \
+ not descended into. Patterns without a slash match the name of files and \
+ directories at any depth, e.g. `.snapshots` or `*.tmp`. Patterns with a \
+ slash match the path relative to the input directory, e.g. `/scratch/*`. \
+ Wildcards also match slashes. Can be given multiple times."
+            )
+            .action(ArgAction::Append)
+            .value_name("pattern")
+            .display_order(4),
+
+        Arg::new("exclude-from")
+            .long("exclude-from")
+            .help("read exclude patterns from file")
+            .long_help(
+"Read exclude patterns from a file, one pattern per line. Empty lines and \
+ lines starting with `#` are ignored. See `--exclude` for the pattern syntax. \
+ Can be given multiple times."
+            )
+            .action(ArgAction::Append)
+            .value_name("file")
+            .display_order(4),
+
+        Arg::new("exclude-fstype")
+            .long("exclude-fstype")
+            .help("do not descend into mounts of file system types")
+            .long_help(
+"Do not descend into mounts of the given file system types, e.g. \
+ `nfs,tmpfs,proc`, according to the mount table. Automount points that are not \
+ mounted yet have the type `autofs`. Entries of skipped mounts are counted as \
+ on other file systems. Multiple types can be given as a comma-separated list. \
+ Linux only. Does not apply to `--spectrum-scale`."
+            )
+            .action(ArgAction::Append)
+            .value_delimiter(',')
+            .value_name("type")
+            .display_order(4),
+
+        Arg::new("exclude-mount")
+            .long("exclude-mount")
+            .help("do not descend into mount")
+            .long_help(
+"Do not descend into the mount on the given mount point, e.g. `/home/shared`, \
+ as listed in the mount table. Relative paths and symbolic links are \
+ resolved. Entries of skipped mounts are counted as on \
+ other file systems. Can be given multiple times. Linux only. Does not apply \
+ to `--spectrum-scale`."
+            )
+            .action(ArgAction::Append)
+            .value_name("path")
+            .display_order(4),
+
+        Arg::new("include")
+            .long("include")
+            .help("count only files matching pattern")
+            .long_help(
+"Count only files matching a glob pattern. Directories are always descended \
+ into, unless they are excluded. Exclude patterns take precedence. See \
+ `--exclude` for the pattern syntax. Can be given multiple times."
+            )
+            .action(ArgAction::Append)
+            .value_name("pattern")
+            .display_order(4),
+    ]
+}
+
+fn limit_args() -> Vec<Arg> {
+    vec![
+        Arg::new("max-depth")
+            .long("max-depth")
+            .help("count only files down to depth")
+            .long_help(
+"Count only files at most the given depth below the input directory, where \
+ files directly in the input directory have depth 1. Deeper directories are \
+ not descended into, their contents are neither counted nor reported. The \
+ limit is recorded in the report."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .display_order(4),
+
+        Arg::new("min-depth")
+            .long("min-depth")
+            .help("count only files from depth")
+            .long_help(
+"Count only files at least the given depth below the input directory, where \
+ files directly in the input directory have depth 1, e.g. `--min-depth 2` \
+ ignores the top-level files. The limit is recorded in the report."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .display_order(4),
+
+        Arg::new("max-size")
+            .long("max-size")
+            .help("count only files up to size")
+            .long_help(
+"Count only files of at most the given apparent size, e.g. to measure the \
+ amount of small files. Supports units, e.g. `4KiB` or `1M` for binary and \
+ `1MB` for decimal units. The limit is recorded in the report in bytes."
+            )
+            .action(ArgAction::Set)
+            .value_name("size")
+            .display_order(4),
+
+        Arg::new("min-size")
+            .long("min-size")
+            .help("count only files from size")
+            .long_help(
+"Count only files of at least the given apparent size, e.g. to find \
+ candidates for archival. Supports units, e.g. `4KiB` or `1M` for binary and \
+ `1MB` for decimal units. The limit is recorded in the report in bytes."
+            )
+            .action(ArgAction::Set)
+            .value_name("size")
+            .display_order(4),
+    ]
+}
+
+fn performance_args() -> Vec<Arg> {
+    vec![
+        Arg::new("threads")
+            .short('j')
+            .long("threads")
+            .help("number of threads")
+            .long_help(
+"Specify the number of threads used for the universal directory traversal. \
+ Metadata-heavy storage, e.g. parallel file systems, usually performs better \
+ with many concurrent metadata requests. Defaults to the number of logical \
+ CPUs."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .display_order(5),
+
+        Arg::new("max-stats-per-second")
+            .long("max-stats-per-second")
+            .help("limit metadata requests")
+            .long_help(
+"Limit the number of file metadata requests (`stat`) per second of all \
+ threads together, to protect the metadata servers of shared storage. Does \
+ not apply to `--spectrum-scale`."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(u32).range(1..))
+            .display_order(2),
+
+        Arg::new("max-dirs-per-second")
+            .long("max-dirs-per-second")
+            .help("limit directory reads")
+            .long_help(
+"Limit the number of directories read per second of all threads together, \
+ to protect the metadata servers of shared storage. Does not apply to \
+ `--spectrum-scale`."
+            )
+            .action(ArgAction::Set)
+            .value_name("N")
+            .value_parser(value_parser!(u32).range(1..))
+            .display_order(2),
+
+        Arg::new("adaptive-throttle")
+            .long("adaptive-throttle")
+            .action(ArgAction::SetTrue)
+            .help("back off when metadata latency rises")
+            .long_help(
+"Slow down when file metadata requests take considerably longer than \
+ usual, i.e. when the storage is busy, and speed up again when they recover. \
+ Latencies that stay high for a while become the new usual, and latencies \
+ below a millisecond never slow down. Can be combined with \
+ `--max-stats-per-second` and `--max-dirs-per-second`. Does not apply to \
+ `--spectrum-scale`."
+            )
+            .display_order(2),
+    ]
+}
+
 fn conditional_compilation_args() -> Vec<Arg> {
+    let mut args = unix_args();
+    args.extend(spectrum_scale_args());
+    args
+}
+
+fn unix_args() -> Vec<Arg> {
     vec![
         #[cfg(target_family = "unix")]
         Arg::new("one-file-system")
@@ -111,6 +559,67 @@ fn conditional_compilation_args() -> Vec<Arg> {
             )
             .display_order(1),
 
+        #[cfg(target_family = "unix")]
+        Arg::new("by-device")
+            .long("by-device")
+            .action(ArgAction::SetTrue)
+            .help("report by mounted file system")
+            .long_help(
+"Report each directory by the device files are on instead of in total, i.e. \
+ by mounted file system, e.g. to see which storage old files occupy. Devices \
+ are shown by their mount point from the mount table, unknown devices by \
+ their device ID. Has no effect with `--one-file-system`."
+            )
+            .display_order(1),
+
+        #[cfg(target_family = "unix")]
+        Arg::new("by-group")
+            .long("by-group")
+            .action(ArgAction::SetTrue)
+            .help("report by file group")
+            .long_help(
+"Report each directory by file group instead of in total. Group names are \
+ resolved from `/etc/group` only, not via NSS, so groups of e.g. LDAP or SSSD \
+ directories are shown by their group ID, as are unknown groups. Combined with `--by-user`, users and groups are reported as \
+ separate rows, each of which sum up to the total."
+            )
+            .display_order(1),
+
+        #[cfg(target_family = "unix")]
+        Arg::new("by-user")
+            .long("by-user")
+            .action(ArgAction::SetTrue)
+            .help("report by file owner")
+            .long_help(
+"Report each directory by file owner instead of in total. User names are \
+ resolved from `/etc/passwd` only, not via NSS, so users of e.g. LDAP or SSSD \
+ directories are shown by their user ID, as are unknown users."
+            )
+            .display_order(1),
+
+        #[cfg(target_family = "unix")]
+        Arg::new("follow-symlinks")
+            .long("follow-symlinks")
+            .help("follow symbolic links")
+            .long_help(
+"Follow symbolic links instead of skipping them. With `--follow-symlinks=root` \
+ only links whose target is inside the input directory are followed. Every \
+ directory is visited only once, which prevents loops. Files reached by \
+ multiple paths are counted each time, unless they are hard links. Does not \
+ apply to `--spectrum-scale`."
+            )
+            .action(ArgAction::Set)
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("all")
+            .value_parser(PossibleValuesParser::new(["all", "root"]))
+            .value_name("all|root")
+            .display_order(1),
+    ]
+}
+
+fn spectrum_scale_args() -> Vec<Arg> {
+    vec![
         #[cfg(feature = "spectrum-scale")]
         Arg::new("spectrum-scale")
             .long("spectrum-scale")
@@ -137,6 +646,32 @@ fn conditional_compilation_args() -> Vec<Arg> {
             )
             .value_name("all|mount|Node,...|NodeFile|NodeClass"),
 
+        #[cfg(feature = "spectrum-scale")]
+        Arg::new("spectrum-scale-m")
+            .long("spectrum-scale-m")
+            .action(ArgAction::Set)
+            .help("use for mmapplypolicy -m argument")
+            .long_help(
+"Specify the number of threads per node for the policy evaluation with \
+ `mmapplypolicy -m`. Fewer threads put less load on the file system. For \
+ detailed information, see `man mmapplypolicy`. Implies `--spectrum-scale`.",
+            )
+            .value_name("ThreadLevel")
+            .value_parser(value_parser!(usize)),
+
+        #[cfg(feature = "spectrum-scale")]
+        Arg::new("spectrum-scale-n")
+            .long("spectrum-scale-n")
+            .action(ArgAction::Set)
+            .help("use for mmapplypolicy -n argument")
+            .long_help(
+"Specify the number of threads per node for the directory scan with \
+ `mmapplypolicy -n`. Fewer threads put less load on the file system. For \
+ detailed information, see `man mmapplypolicy`. Implies `--spectrum-scale`.",
+            )
+            .value_name("DirThreadLevel")
+            .value_parser(value_parser!(usize)),
+
         #[cfg(feature = "spectrum-scale")]
         Arg::new("spectrum-scale-g")
             .long("spectrum-scale-g")
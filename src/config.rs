@@ -1,4 +1,12 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, anyhow};
 use clap::ArgMatches;
+use stor_age::output::Size;
+use stor_age::{
+    Filter, Options, Symlinks, Time, days_before, parse_bytes, parse_timestamp,
+};
 
 use crate::Output;
 
@@ -7,10 +15,14 @@ use crate::Output;
 pub struct Config {
     pub debug: bool,
     pub progress: bool,
-    pub ages_in_days: Vec<u64>,
     pub output: Output,
-
-    pub one_file_system: bool,
+    pub size: Size,
+    pub options: Options,
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: Duration,
+    pub resume: bool,
+    pub dedupe_inputs: bool,
+    pub counts: bool,
 
     #[cfg(feature = "spectrum-scale")]
     pub spectrum_scale: bool,
@@ -18,6 +30,12 @@ pub struct Config {
     #[cfg(feature = "spectrum-scale")]
     pub spectrum_scale_nodes: Option<String>,
 
+    #[cfg(feature = "spectrum-scale")]
+    pub spectrum_scale_thread_level: Option<usize>,
+
+    #[cfg(feature = "spectrum-scale")]
+    pub spectrum_scale_dir_thread_level: Option<usize>,
+
     #[cfg(feature = "spectrum-scale")]
     pub spectrum_scale_global_work_dir: Option<String>,
 
@@ -28,42 +46,50 @@ pub struct Config {
 impl Config {
     /// Returns configuration from `clap` arguments.
     ///
+    /// # Errors
+    ///
+    /// Returns an error if filter patterns are invalid, an exclude file can
+    /// not be read, or the ages reach too far before the reference time.
+    ///
     /// # Panics
     ///
     /// Panics if required arguments are not present.
-    #[must_use]
-    pub fn from_args(args: &ArgMatches) -> Self {
-        let mut ages_in_days: Vec<u64> = args
-            .get_many::<u64>("age")
-            .expect("age is required")
-            .copied()
-            .collect();
-        ages_in_days.sort_unstable();
-        ages_in_days.dedup();
-
+    pub fn from_args(args: &ArgMatches) -> Result<Self> {
         let output = args
             .get_one::<Output>("format")
             .copied()
             .expect("format is required or has default");
 
+        let size = args
+            .get_one::<String>("size")
+            .expect("size has default")
+            .parse()
+            .expect("size values are restricted");
+
         let debug = args.get_flag("debug");
         let progress = args.get_flag("progress") || debug;
 
-        let one_file_system =
-            args.try_contains_id("one-file-system").unwrap_or_default()
-                && args.get_flag("one-file-system");
-
-        Self {
+        Ok(Self {
             debug,
             progress,
-            ages_in_days,
             output,
-
-            one_file_system,
+            size,
+            options: options(args)?,
+            checkpoint: args.get_one::<String>("checkpoint").cloned(),
+            checkpoint_interval: Duration::from_secs(
+                args.get_one::<u64>("checkpoint-interval")
+                    .copied()
+                    .unwrap_or(60),
+            ),
+            resume: args.get_flag("resume"),
+            dedupe_inputs: args.get_flag("dedupe-inputs"),
+            counts: args.get_flag("counts"),
 
             #[cfg(feature = "spectrum-scale")]
             spectrum_scale: args.get_flag("spectrum-scale")
                 || args.contains_id("spectrum-scale-N")
+                || args.contains_id("spectrum-scale-m")
+                || args.contains_id("spectrum-scale-n")
                 || args.contains_id("spectrum-scale-g")
                 || args.contains_id("spectrum-scale-s"),
 
@@ -72,6 +98,16 @@ impl Config {
                 .get_one::<String>("spectrum-scale-N")
                 .cloned(),
 
+            #[cfg(feature = "spectrum-scale")]
+            spectrum_scale_thread_level: args
+                .get_one::<usize>("spectrum-scale-m")
+                .copied(),
+
+            #[cfg(feature = "spectrum-scale")]
+            spectrum_scale_dir_thread_level: args
+                .get_one::<usize>("spectrum-scale-n")
+                .copied(),
+
             #[cfg(feature = "spectrum-scale")]
             spectrum_scale_global_work_dir: args
                 .get_one::<String>("spectrum-scale-g")
@@ -81,6 +117,192 @@ impl Config {
             spectrum_scale_local_work_dir: args
                 .get_one::<String>("spectrum-scale-s")
                 .cloned(),
+        })
+    }
+}
+
+fn options(args: &ArgMatches) -> Result<Options> {
+    let mut ages_in_days: Vec<u64> = args
+        .get_many::<u64>("age")
+        .expect("age is required")
+        .copied()
+        .collect();
+    ages_in_days.sort_unstable();
+    ages_in_days.dedup();
+
+    let mut times: Vec<Time> = args
+        .get_many::<String>("time")
+        .expect("time has default")
+        .map(|time| time.parse().expect("time values are restricted"))
+        .collect();
+    times.sort_unstable();
+    times.dedup();
+
+    let reference_time = args
+        .get_one::<String>("reference-time")
+        .map(|time| parse_timestamp(time))
+        .transpose()?;
+
+    if let Some(oldest) = ages_in_days.last() {
+        let now = reference_time.unwrap_or_else(SystemTime::now);
+        days_before(now, *oldest)?;
+    }
+
+    let mut options = Options {
+        ages_in_days,
+        buckets: args.get_flag("buckets"),
+        reference_time,
+        times,
+        filter: filter(args)?,
+        strict: args.get_flag("strict"),
+        top: args.get_one::<usize>("top").copied(),
+        threads: args
+            .get_one::<usize>("threads")
+            .copied()
+            .unwrap_or_default(),
+        ..Options::default()
+    };
+
+    breakdowns(args, &mut options)?;
+    limits(args, &mut options)?;
+    traversal(args, &mut options)?;
+    throttle(args, &mut options);
+
+    Ok(options)
+}
+
+fn breakdowns(args: &ArgMatches, options: &mut Options) -> Result<()> {
+    options.depth = args.get_one::<usize>("depth").copied();
+
+    options.by_user = args.try_contains_id("by-user").unwrap_or_default()
+        && args.get_flag("by-user");
+
+    options.by_group = args.try_contains_id("by-group").unwrap_or_default()
+        && args.get_flag("by-group");
+
+    options.by_device = args.try_contains_id("by-device").unwrap_or_default()
+        && args.get_flag("by-device");
+
+    options.top_extensions = args.get_one::<usize>("top-extensions").copied();
+    options.by_extension =
+        args.get_flag("by-extension") || options.top_extensions.is_some();
+
+    for size in args.get_many::<String>("size-buckets").unwrap_or_default() {
+        options.size_buckets.push(parse_bytes(size)?);
+    }
+
+    options.size_buckets.sort_unstable();
+    options.size_buckets.dedup();
+
+    Ok(())
+}
+
+fn limits(args: &ArgMatches, options: &mut Options) -> Result<()> {
+    let min_depth = args.get_one::<usize>("min-depth").copied();
+    let max_depth = args.get_one::<usize>("max-depth").copied();
+
+    if let (Some(min), Some(max)) = (min_depth, max_depth) {
+        if min > max {
+            return Err(anyhow!(
+                "minimum depth {min} is larger than maximum depth {max}"
+            ));
+        }
+    }
+
+    let min_size = args
+        .get_one::<String>("min-size")
+        .map(|size| parse_bytes(size))
+        .transpose()?;
+
+    let max_size = args
+        .get_one::<String>("max-size")
+        .map(|size| parse_bytes(size))
+        .transpose()?;
+
+    if let (Some(min), Some(max)) = (min_size, max_size) {
+        if min > max {
+            return Err(anyhow!(
+                "minimum size {min} is larger than maximum size {max}"
+            ));
+        }
+    }
+
+    options.min_depth = min_depth;
+    options.max_depth = max_depth;
+    options.min_size = min_size;
+    options.max_size = max_size;
+
+    Ok(())
+}
+
+fn traversal(args: &ArgMatches, options: &mut Options) -> Result<()> {
+    options.one_file_system =
+        args.try_contains_id("one-file-system").unwrap_or_default()
+            && args.get_flag("one-file-system");
+
+    options.symlinks = match args
+        .try_get_one::<String>("follow-symlinks")
+        .ok()
+        .flatten()
+        .map(String::as_str)
+    {
+        Some("all") => Symlinks::Follow,
+        Some("root") => Symlinks::FollowWithinRoot,
+        _ => Symlinks::Skip,
+    };
+
+    options.exclude_fs_types = args
+        .get_many::<String>("exclude-fstype")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    // mount points are compared to the mount table, which lists them as
+    // absolute paths without symbolic links
+    options.exclude_mounts = args
+        .get_many::<String>("exclude-mount")
+        .unwrap_or_default()
+        .map(|path| {
+            fs::canonicalize(path).with_context(|| {
+                format!("error resolving exclude mount {path}")
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(())
+}
+
+fn throttle(args: &ArgMatches, options: &mut Options) {
+    options.max_stats_per_second =
+        args.get_one::<u32>("max-stats-per-second").copied();
+
+    options.max_dirs_per_second =
+        args.get_one::<u32>("max-dirs-per-second").copied();
+
+    options.adaptive_throttle = args.get_flag("adaptive-throttle");
+}
+
+fn filter(args: &ArgMatches) -> Result<Filter> {
+    let mut filter = Filter::default();
+
+    for pattern in args.get_many::<String>("exclude").unwrap_or_default() {
+        filter = filter.with_exclude(pattern)?;
+    }
+
+    for file in args.get_many::<String>("exclude-from").unwrap_or_default() {
+        let patterns = fs::read_to_string(file)
+            .with_context(|| format!("error reading exclude file {file}"))?;
+
+        for pattern in patterns.lines().map(str::trim) {
+            if !pattern.is_empty() && !pattern.starts_with('#') {
+                filter = filter.with_exclude(pattern)?;
+            }
         }
     }
+
+    for pattern in args.get_many::<String>("include").unwrap_or_default() {
+        filter = filter.with_include(pattern)?;
+    }
+
+    Ok(filter)
 }
@@ -1,82 +1,416 @@
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::io::ErrorKind;
 use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
 
-#[derive(Debug, Default)]
-struct Count {
-    accessed_bytes: u64,
-    modified_bytes: u64,
-    accessed_files: u64,
-    modified_files: u64,
+use crate::AtimeMode;
+
+/// Size and number of files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Amount {
+    /// Apparent size in bytes.
+    pub bytes: u64,
+
+    /// Allocated size in bytes.
+    pub allocated: u64,
+
+    /// Number of files.
+    pub files: u64,
 }
 
-impl AddAssign for Count {
+impl AddAssign for Amount {
+    fn add_assign(&mut self, other: Self) {
+        *self = Self {
+            bytes: self.bytes + other.bytes,
+            allocated: self.allocated + other.allocated,
+            files: self.files + other.files,
+        }
+    }
+}
+
+/// Entries skipped during traversal, by reason. Skipped symbolic links are
+/// counted separately, see [`Data::get_skipped_symlinks`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Skipped {
+    /// Directories that could not be read due to missing permissions.
+    pub permission_denied: u64,
+
+    /// Entries on other file systems than the input directory, or on
+    /// excluded mounts.
+    pub other_file_system: u64,
+
+    /// Entries that are neither regular files, directories nor symbolic
+    /// links, e.g. sockets or device files.
+    pub special_files: u64,
+}
+
+impl AddAssign for Skipped {
     fn add_assign(&mut self, other: Self) {
         *self = Self {
-            accessed_bytes: self.accessed_bytes + other.accessed_bytes,
-            modified_bytes: self.modified_bytes + other.modified_bytes,
-            accessed_files: self.accessed_files + other.accessed_files,
-            modified_files: self.modified_files + other.modified_files,
+            permission_denied: self.permission_denied
+                + other.permission_denied,
+            other_file_system: self.other_file_system
+                + other.other_file_system,
+            special_files: self.special_files + other.special_files,
+        }
+    }
+}
+
+/// File among the largest ones past an age threshold, see [`Data::get_top`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TopFile {
+    pub path: PathBuf,
+
+    /// Apparent size in bytes.
+    pub bytes: u64,
+
+    /// Access time, if available.
+    pub accessed: Option<SystemTime>,
+
+    /// Modification time, if available.
+    pub modified: Option<SystemTime>,
+}
+
+/// Orders by size and then by path, so that the order is deterministic.
+impl Ord for TopFile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.bytes
+            .cmp(&other.bytes)
+            .then_with(|| self.path.cmp(&other.path))
+    }
+}
+
+impl PartialOrd for TopFile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Bounded heap of the `n` largest files.
+#[derive(Clone, Debug, Default)]
+struct Top {
+    n: usize,
+    files: BinaryHeap<Reverse<TopFile>>,
+}
+
+impl Top {
+    fn push(&mut self, file: TopFile) {
+        self.files.push(Reverse(file));
+
+        if self.files.len() > self.n {
+            self.files.pop();
+        }
+    }
+}
+
+/// Time dimension of file metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Time {
+    /// Access time.
+    Accessed,
+
+    /// Modification time.
+    Modified,
+
+    /// Status change time.
+    Changed,
+
+    /// Creation time, if supported by the file system.
+    Created,
+}
+
+impl Time {
+    pub const ALL: [Self; 4] =
+        [Self::Accessed, Self::Modified, Self::Changed, Self::Created];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Accessed => "accessed",
+            Self::Modified => "modified",
+            Self::Changed => "changed",
+            Self::Created => "created",
+        }
+    }
+}
+
+impl FromStr for Time {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "accessed" => Ok(Self::Accessed),
+            "modified" => Ok(Self::Modified),
+            "changed" => Ok(Self::Changed),
+            "created" => Ok(Self::Created),
+            _ => Err(String::from("invalid time")),
+        }
+    }
+}
+
+/// Key of a breakdown of data within a directory.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    /// Files owned by the user ID.
+    User(u32),
+
+    /// Files owned by the group ID.
+    Group(u32),
+
+    /// Files with the extension, including the dot, or without extension if
+    /// empty.
+    Extension(String),
+
+    /// Files with extensions that are not reported separately.
+    OtherExtensions,
+
+    /// Files with a size of at least `min` and less than `max` bytes.
+    Size { min: u64, max: Option<u64> },
+
+    /// Files on the device ID, see [`crate::mounts::mount_points`].
+    Device(u64),
+}
+
+#[derive(Clone, Debug, Default)]
+struct Count {
+    accessed: Amount,
+    modified: Amount,
+    changed: Amount,
+    created: Amount,
+}
+
+impl Count {
+    const fn get(&self, time: Time) -> Amount {
+        match time {
+            Time::Accessed => self.accessed,
+            Time::Modified => self.modified,
+            Time::Changed => self.changed,
+            Time::Created => self.created,
+        }
+    }
+
+    const fn get_mut(&mut self, time: Time) -> &mut Amount {
+        match time {
+            Time::Accessed => &mut self.accessed,
+            Time::Modified => &mut self.modified,
+            Time::Changed => &mut self.changed,
+            Time::Created => &mut self.created,
         }
     }
 }
 
-#[derive(Debug, Default)]
+impl AddAssign for Count {
+    fn add_assign(&mut self, other: Self) {
+        self.accessed += other.accessed;
+        self.modified += other.modified;
+        self.changed += other.changed;
+        self.created += other.created;
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Data {
-    total_bytes: u64,
-    total_files: u64,
+    total: Amount,
+    hard_links: u64,
+    followed_symlinks: u64,
+    skipped_symlinks: u64,
+    skipped: Skipped,
+    errors: BTreeMap<String, u64>,
+    top: BTreeMap<u64, Top>,
+    atime_mode: Option<AtimeMode>,
     inner: HashMap<u64, Count>,
+    buckets: bool,
+    subdirectories: Option<BTreeMap<PathBuf, Data>>,
+    breakdown: Option<BTreeMap<Key, Data>>,
 }
 
 impl Data {
     #[must_use]
     pub fn with_ages(mut self, ages: &[u64]) -> Self {
         for age in ages {
-            self.insert(*age, 0, 0, 0, 0);
+            self.inner.entry(*age).or_default();
         }
 
         self
     }
 
+    /// Sets whether ages are lower bounds of disjoint buckets instead of
+    /// cumulative thresholds, see [`Data::get`].
+    #[must_use]
+    pub const fn with_buckets(mut self, buckets: bool) -> Self {
+        self.buckets = buckets;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_total(mut self, total: Amount) -> Self {
+        self.total = total;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_hard_links(mut self, links: u64) -> Self {
+        self.hard_links = links;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_followed_symlinks(mut self, symlinks: u64) -> Self {
+        self.followed_symlinks = symlinks;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_skipped_symlinks(mut self, symlinks: u64) -> Self {
+        self.skipped_symlinks = symlinks;
+        self
+    }
+
     #[must_use]
-    pub const fn with_total_bytes(mut self, bytes: u64) -> Self {
-        self.total_bytes = bytes;
+    pub const fn with_skipped(mut self, skipped: Skipped) -> Self {
+        self.skipped = skipped;
         self
     }
 
+    /// Sets how access times are updated in the input directory.
     #[must_use]
-    pub const fn with_total_files(mut self, files: u64) -> Self {
-        self.total_files = files;
+    pub const fn with_atime_mode(mut self, mode: Option<AtimeMode>) -> Self {
+        self.atime_mode = mode;
         self
     }
 
+    /// Enables the breakdown, e.g. by file owner, even if there are no files.
     #[must_use]
-    pub fn get_accessed_bytes(&self, age: u64) -> Option<u64> {
-        self.inner.get(&age).map(|data| data.accessed_bytes)
+    pub(crate) fn with_breakdown(mut self) -> Self {
+        self.breakdown.get_or_insert_default();
+        self
     }
 
+    /// Sets the breakdown by subdirectory, see [`Data::get_subdirectories`].
     #[must_use]
-    pub fn get_modified_bytes(&self, age: u64) -> Option<u64> {
-        self.inner.get(&age).map(|data| data.modified_bytes)
+    pub(crate) fn with_subdirectories(
+        mut self,
+        subdirectories: BTreeMap<PathBuf, Self>,
+    ) -> Self {
+        self.subdirectories = Some(subdirectories);
+        self
     }
 
+    /// Returns the amount of files whose `time` is younger than `age`.
+    ///
+    /// With buckets, returns the amount of files whose `time` is at least
+    /// `age` and younger than the next larger age instead. The bucket of the
+    /// largest age is open-ended.
     #[must_use]
-    pub fn get_accessed_files(&self, age: u64) -> Option<u64> {
-        self.inner.get(&age).map(|data| data.accessed_files)
+    pub fn get(&self, age: u64, time: Time) -> Option<Amount> {
+        self.inner.get(&age).map(|count| count.get(time))
     }
 
+    /// Returns the amount of files whose `time` is younger than `age`, which
+    /// is summed up from the buckets if necessary.
     #[must_use]
-    pub fn get_modified_files(&self, age: u64) -> Option<u64> {
-        self.inner.get(&age).map(|data| data.modified_files)
+    pub fn get_younger(&self, age: u64, time: Time) -> Option<Amount> {
+        if !self.buckets {
+            return self.get(age, time);
+        }
+
+        if !self.inner.contains_key(&age) {
+            return None;
+        }
+
+        let mut sum = Amount::default();
+
+        for (_, count) in self.inner.iter().filter(|(min, _)| **min < age) {
+            sum += count.get(time);
+        }
+
+        Some(sum)
+    }
+
+    /// Returns `true` if ages are lower bounds of disjoint buckets.
+    #[must_use]
+    pub const fn has_buckets(&self) -> bool {
+        self.buckets
     }
 
     #[must_use]
-    pub const fn get_total_bytes(&self) -> u64 {
-        self.total_bytes
+    pub const fn get_total(&self) -> Amount {
+        self.total
     }
 
+    /// Returns the number of additional hard links to already counted files.
     #[must_use]
-    pub const fn get_total_files(&self) -> u64 {
-        self.total_files
+    pub const fn get_hard_links(&self) -> u64 {
+        self.hard_links
+    }
+
+    #[must_use]
+    pub const fn get_followed_symlinks(&self) -> u64 {
+        self.followed_symlinks
+    }
+
+    #[must_use]
+    pub const fn get_skipped_symlinks(&self) -> u64 {
+        self.skipped_symlinks
+    }
+
+    /// Returns the number of entries skipped during traversal, other than
+    /// symbolic links.
+    #[must_use]
+    pub const fn get_skipped(&self) -> Skipped {
+        self.skipped
+    }
+
+    /// Returns the number of errors of single entries that were tolerated
+    /// during traversal, e.g. files that vanished, by kind of error.
+    #[must_use]
+    pub const fn get_errors(&self) -> &BTreeMap<String, u64> {
+        &self.errors
+    }
+
+    /// Returns how access times are updated in the input directory, if it is
+    /// known. Unless they are updated on every access, access ages are not
+    /// reliable.
+    #[must_use]
+    pub const fn get_atime_mode(&self) -> Option<AtimeMode> {
+        self.atime_mode
+    }
+
+    /// Returns the largest files past each age threshold, largest first, if
+    /// they were requested.
+    #[must_use]
+    pub fn get_top(&self) -> Vec<(u64, Vec<&TopFile>)> {
+        self.top
+            .iter()
+            .map(|(age, top)| {
+                let mut files: Vec<&TopFile> =
+                    top.files.iter().map(|file| &file.0).collect();
+
+                files.sort_by(|a, b| b.cmp(a));
+
+                (*age, files)
+            })
+            .collect()
+    }
+
+    /// Returns the breakdown by subdirectory, if it was requested. Keys are
+    /// paths relative to the input directory. Each subdirectory includes the
+    /// data of its own subdirectories.
+    #[must_use]
+    pub const fn get_subdirectories(
+        &self,
+    ) -> Option<&BTreeMap<PathBuf, Self>> {
+        self.subdirectories.as_ref()
+    }
+
+    /// Returns the breakdown, e.g. by file owner, if it was requested. With
+    /// multiple kinds of keys, each kind sums up to the total independently.
+    #[must_use]
+    pub const fn get_breakdown(&self) -> Option<&BTreeMap<Key, Self>> {
+        self.breakdown.as_ref()
     }
 
     #[must_use]
@@ -86,33 +420,302 @@ impl Data {
         ages
     }
 
-    pub fn insert(
-        &mut self,
-        age: u64,
-        accessed_bytes: u64,
-        modified_bytes: u64,
-        accessed_files: u64,
-        modified_files: u64,
-    ) {
-        let a = Count {
-            accessed_bytes,
-            modified_bytes,
-            accessed_files,
-            modified_files,
-        };
+    pub fn insert(&mut self, age: u64, time: Time, amount: Amount) {
+        *self.inner.entry(age).or_default().get_mut(time) = amount;
+    }
+
+    /// Counts a tolerated error of a single entry.
+    pub(crate) fn add_error(&mut self, kind: ErrorKind) {
+        self.add_errors(kind.to_string(), 1);
+    }
+
+    /// Counts `count` tolerated errors of a kind, see [`Data::get_errors`].
+    pub(crate) fn add_errors(&mut self, kind: String, count: u64) {
+        *self.errors.entry(kind).or_default() += count;
+    }
+
+    /// Adds a file past the age threshold `age`, keeping only the `n`
+    /// largest files.
+    pub(crate) fn add_top(&mut self, age: u64, n: usize, file: TopFile) {
+        let top = self.top.entry(age).or_insert_with(|| Top {
+            n,
+            files: BinaryHeap::new(),
+        });
+
+        top.push(file);
+    }
+
+    /// Adds data of files to the breakdown under `key`.
+    pub(crate) fn add_breakdown(&mut self, key: Key, data: Self) {
+        let breakdown = self.breakdown.get_or_insert_default();
+        let inner = &self.inner;
+        let buckets = self.buckets;
+
+        *breakdown.entry(key).or_insert_with(|| {
+            let ages: Vec<u64> = inner.keys().copied().collect();
+            Self::default().with_ages(&ages).with_buckets(buckets)
+        }) += data;
+    }
+
+    /// Keeps the `n` extensions with the largest apparent size in the
+    /// breakdown and sums up the others as [`Key::OtherExtensions`], also in
+    /// all subdirectories.
+    pub(crate) fn truncate_extensions(&mut self, n: usize) {
+        if let Some(breakdown) = &mut self.breakdown {
+            let mut extensions: Vec<(Key, u64)> = breakdown
+                .iter()
+                .filter(|(key, _)| matches!(key, Key::Extension(_)))
+                .map(|(key, data)| (key.clone(), data.total.bytes))
+                .collect();
+
+            extensions.sort_by_key(|(_, bytes)| Reverse(*bytes));
 
-        self.inner.insert(age, a);
+            for (key, _) in extensions.into_iter().skip(n) {
+                if let Some(data) = breakdown.remove(&key) {
+                    *breakdown.entry(Key::OtherExtensions).or_default() +=
+                        data;
+                }
+            }
+        }
+
+        for data in self
+            .subdirectories
+            .iter_mut()
+            .flat_map(BTreeMap::values_mut)
+        {
+            data.truncate_extensions(n);
+        }
+    }
+
+    /// Sums up data of directories to the data of the input directory with
+    /// a breakdown by subdirectory.
+    ///
+    /// Keys are paths relative to the input directory, i.e. the input
+    /// directory itself is the empty path. Values contain only the files
+    /// attributed directly to the directory, like `du` the sums include the
+    /// data of all subdirectories.
+    pub(crate) fn roll_up(mut directories: HashMap<PathBuf, Self>) -> Self {
+        let paths: Vec<PathBuf> = directories.keys().cloned().collect();
+
+        for path in &paths {
+            for ancestor in path.ancestors().skip(1) {
+                directories.entry(ancestor.to_path_buf()).or_default();
+            }
+        }
+
+        let mut paths: Vec<PathBuf> = directories
+            .keys()
+            .filter(|path| !path.as_os_str().is_empty())
+            .cloned()
+            .collect();
+
+        // deepest first, so that all children are added before their parent
+        paths.sort_by_key(|path| Reverse(path.components().count()));
+
+        let mut subdirectories = BTreeMap::new();
+
+        for path in paths {
+            let data = directories.remove(&path).unwrap_or_default();
+            let parent = path.parent().unwrap_or_else(|| Path::new(""));
+
+            *directories.entry(parent.to_path_buf()).or_default() +=
+                data.clone();
+
+            subdirectories.insert(path, data);
+        }
+
+        let mut data = directories.remove(Path::new("")).unwrap_or_default();
+        data.subdirectories = Some(subdirectories);
+        data
     }
 }
 
 impl AddAssign for Data {
     fn add_assign(&mut self, other: Self) {
-        self.total_bytes += other.total_bytes;
-        self.total_files += other.total_files;
+        self.total += other.total;
+        self.hard_links += other.hard_links;
+        self.followed_symlinks += other.followed_symlinks;
+        self.skipped_symlinks += other.skipped_symlinks;
+        self.skipped += other.skipped;
+        self.buckets |= other.buckets;
+        self.atime_mode = self.atime_mode.or(other.atime_mode);
+
+        for (kind, count) in other.errors {
+            *self.errors.entry(kind).or_default() += count;
+        }
+
+        for (age, other) in other.top {
+            let top = self.top.entry(age).or_insert_with(|| Top {
+                n: other.n,
+                files: BinaryHeap::new(),
+            });
+
+            for file in other.files {
+                top.push(file.0);
+            }
+        }
 
         for (age, acc) in other.inner {
             let sum = self.inner.entry(age).or_default();
             *sum += acc;
         }
+
+        if let Some(other) = other.subdirectories {
+            let subdirectories = self.subdirectories.get_or_insert_default();
+
+            for (path, data) in other {
+                *subdirectories.entry(path).or_default() += data;
+            }
+        }
+
+        if let Some(other) = other.breakdown {
+            let breakdown = self.breakdown.get_or_insert_default();
+
+            for (key, data) in other {
+                *breakdown.entry(key).or_default() += data;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(files: u64) -> Data {
+        Data::default().with_total(Amount {
+            files,
+            ..Amount::default()
+        })
+    }
+
+    #[test]
+    fn roll_up() {
+        let directories = HashMap::from([
+            (PathBuf::new(), files(1)),
+            (PathBuf::from("a"), files(2)),
+            (PathBuf::from("a/b"), files(4)),
+            (PathBuf::from("c/d"), files(8)),
+        ]);
+
+        let data = Data::roll_up(directories);
+        assert_eq!(data.get_total().files, 15);
+
+        let subdirectories = data.get_subdirectories().unwrap();
+
+        let files: Vec<_> = subdirectories
+            .iter()
+            .map(|(path, data)| (path.to_str().unwrap(), data.total.files))
+            .collect();
+
+        assert_eq!(files, [("a", 6), ("a/b", 4), ("c", 8), ("c/d", 8)]);
+    }
+
+    #[test]
+    fn errors() {
+        let mut a = Data::default();
+        a.add_error(ErrorKind::NotFound);
+
+        let mut b = Data::default();
+        b.add_error(ErrorKind::NotFound);
+        b.add_error(ErrorKind::StaleNetworkFileHandle);
+
+        let data = Data::roll_up(HashMap::from([
+            (PathBuf::from("a"), a),
+            (PathBuf::from("a/b"), b),
+        ]));
+
+        let errors: Vec<_> = data
+            .get_errors()
+            .iter()
+            .map(|(kind, count)| (kind.as_str(), *count))
+            .collect();
+
+        assert_eq!(
+            errors,
+            [("entity not found", 2), ("stale network file handle", 1)]
+        );
+    }
+
+    #[test]
+    fn top() {
+        let file = |path: &str, bytes| TopFile {
+            path: PathBuf::from(path),
+            bytes,
+            accessed: None,
+            modified: None,
+        };
+
+        let mut a = Data::default();
+        a.add_top(90, 2, file("a", 1));
+        a.add_top(90, 2, file("b", 3));
+        a.add_top(90, 2, file("c", 2));
+
+        let mut b = Data::default();
+        b.add_top(90, 2, file("d", 4));
+
+        a += b;
+
+        let top = a.get_top();
+        let paths: Vec<_> = top[0]
+            .1
+            .iter()
+            .map(|file| file.path.to_str().unwrap())
+            .collect();
+
+        assert_eq!(top[0].0, 90);
+        assert_eq!(paths, ["d", "b"]);
+    }
+
+    #[test]
+    fn younger() {
+        let files = |files| Amount {
+            files,
+            ..Amount::default()
+        };
+
+        let mut data = Data::default().with_buckets(true);
+        data.insert(0, Time::Accessed, files(1));
+        data.insert(90, Time::Accessed, files(2));
+        data.insert(365, Time::Accessed, files(4));
+
+        let younger = |age| data.get_younger(age, Time::Accessed);
+
+        assert_eq!(younger(90), Some(files(1)));
+        assert_eq!(younger(365), Some(files(3)));
+        assert_eq!(younger(30), None);
+    }
+
+    #[test]
+    fn truncate_extensions() {
+        let bytes = |bytes| {
+            Data::default().with_total(Amount {
+                bytes,
+                ..Amount::default()
+            })
+        };
+
+        let mut data = Data::default();
+        data.add_breakdown(Key::User(0), bytes(7));
+        data.add_breakdown(Key::Extension(".bam".into()), bytes(4));
+        data.add_breakdown(Key::Extension(".nc".into()), bytes(2));
+        data.add_breakdown(Key::Extension(String::new()), bytes(1));
+
+        data.truncate_extensions(1);
+
+        let breakdown: Vec<_> = data
+            .get_breakdown()
+            .unwrap()
+            .iter()
+            .map(|(key, data)| (key.clone(), data.total.bytes))
+            .collect();
+
+        let expected = [
+            (Key::User(0), 7),
+            (Key::Extension(".bam".into()), 4),
+            (Key::OtherExtensions, 3),
+        ];
+
+        assert_eq!(breakdown, expected);
     }
 }
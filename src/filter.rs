@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobMatcher};
+
+/// Glob patterns to exclude or include files and directories.
+///
+/// Patterns without a slash match the name of a file or directory at any
+/// depth. Patterns with a slash match the path relative to the input
+/// directory. Wildcards also match slashes.
+///
+/// Excluded directories are not descended into. If there are any include
+/// patterns, only files matching one of them are counted.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    exclude: Vec<Pattern>,
+    include: Vec<Pattern>,
+}
+
+impl Filter {
+    /// Adds an exclude pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is not a valid glob.
+    pub fn with_exclude(mut self, glob: &str) -> Result<Self> {
+        self.exclude.push(Pattern::new(glob)?);
+        Ok(self)
+    }
+
+    /// Adds an include pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is not a valid glob.
+    pub fn with_include(mut self, glob: &str) -> Result<Self> {
+        self.include.push(Pattern::new(glob)?);
+        Ok(self)
+    }
+
+    /// Returns `true` if the path, relative to the input directory, matches
+    /// any exclude pattern.
+    #[must_use]
+    pub fn is_excluded(&self, relative: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.is_match(relative))
+    }
+
+    /// Returns `true` if the path, relative to the input directory, matches
+    /// any include pattern or if there are no include patterns.
+    #[must_use]
+    pub fn is_included(&self, relative: &Path) -> bool {
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.is_match(relative))
+    }
+
+    pub(crate) fn excludes(&self) -> &[Pattern] {
+        &self.exclude
+    }
+
+    pub(crate) fn includes(&self) -> &[Pattern] {
+        &self.include
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    glob: String,
+    path: bool,
+    matcher: GlobMatcher,
+}
+
+impl Pattern {
+    fn new(glob: &str) -> Result<Self> {
+        let path = glob.contains('/');
+        let glob = glob.trim_start_matches('/').trim_end_matches('/');
+
+        let matcher = Glob::new(glob)
+            .with_context(|| format!("invalid pattern: {glob}"))?
+            .compile_matcher();
+
+        Ok(Self {
+            glob: glob.to_owned(),
+            path,
+            matcher,
+        })
+    }
+
+    /// Returns the glob without leading or trailing slashes.
+    pub fn glob(&self) -> &str {
+        &self.glob
+    }
+
+    /// Returns `true` if the pattern matches the relative path instead of
+    /// the name.
+    pub const fn is_path(&self) -> bool {
+        self.path
+    }
+
+    fn is_match(&self, relative: &Path) -> bool {
+        if self.path {
+            self.matcher.is_match(relative)
+        } else {
+            relative
+                .file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exclude_name() {
+        let filter = Filter::default().with_exclude(".git").unwrap();
+
+        assert!(filter.is_excluded(Path::new(".git")));
+        assert!(filter.is_excluded(Path::new("foo/bar/.git")));
+        assert!(!filter.is_excluded(Path::new("foo/.gitignore")));
+    }
+
+    #[test]
+    fn exclude_path() {
+        let filter = Filter::default().with_exclude("/foo/*.tmp").unwrap();
+
+        assert!(filter.is_excluded(Path::new("foo/bar.tmp")));
+        assert!(filter.is_excluded(Path::new("foo/bar/baz.tmp")));
+        assert!(!filter.is_excluded(Path::new("bar/baz.tmp")));
+    }
+
+    #[test]
+    fn include() {
+        let filter = Filter::default();
+        assert!(filter.is_included(Path::new("foo.bam")));
+
+        let filter = filter.with_include("*.bam").unwrap();
+        assert!(filter.is_included(Path::new("foo/bar.bam")));
+        assert!(!filter.is_included(Path::new("foo/bar.nc")));
+    }
+}
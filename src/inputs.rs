@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Input directory inside another one, see [`nested_inputs`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NestedInput {
+    /// Index of the nested input directory.
+    pub dir: usize,
+
+    /// Index of the outermost input directory containing it.
+    pub parent: usize,
+}
+
+/// Returns the input directories that are inside another input directory.
+///
+/// These would be scanned twice. Each is returned together with the
+/// outermost input directory containing it. Of input directories that are
+/// the same, all but the first one are returned.
+///
+/// Directories are compared by their canonical paths, so that e.g. trailing
+/// slashes or symbolic links do not matter. Directories that can not be
+/// resolved are ignored.
+#[must_use]
+pub fn nested_inputs(dirs: &[&str]) -> Vec<NestedInput> {
+    let canonical: Vec<Option<PathBuf>> =
+        dirs.iter().map(|dir| fs::canonicalize(dir).ok()).collect();
+
+    let mut first: HashMap<&PathBuf, usize> = HashMap::new();
+
+    for (i, path) in canonical.iter().enumerate() {
+        if let Some(path) = path {
+            first.entry(path).or_insert(i);
+        }
+    }
+
+    let mut nested = vec![];
+
+    for (dir, path) in canonical.iter().enumerate() {
+        let Some(path) = path else {
+            continue;
+        };
+
+        // ancestors start with the path itself and end with the root, so the
+        // last match is the outermost one
+        let parent = path
+            .ancestors()
+            .filter_map(|ancestor| first.get(&ancestor.to_path_buf()))
+            .copied()
+            .filter(|parent| *parent != dir)
+            .last();
+
+        if let Some(parent) = parent {
+            nested.push(NestedInput { dir, parent });
+        }
+    }
+
+    nested
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("a/b/c")).unwrap();
+        fs::create_dir_all(tmp.path().join("d")).unwrap();
+
+        let root = tmp.path().display().to_string();
+        let a = format!("{root}/a");
+        let a_slash = format!("{root}/a/");
+        let c = format!("{root}/a/b/c");
+        let d = format!("{root}/d");
+        let missing = format!("{root}/missing");
+
+        let nested = |dirs: &[&str]| -> Vec<(usize, usize)> {
+            nested_inputs(dirs)
+                .into_iter()
+                .map(|nested| (nested.dir, nested.parent))
+                .collect()
+        };
+
+        assert_eq!(nested(&[&a, &d, &missing]), []);
+        assert_eq!(nested(&[&c, &a]), [(0, 1)]);
+        assert_eq!(nested(&[&a, &a_slash, &a]), [(1, 0), (2, 0)]);
+        assert_eq!(nested(&[&c, &a, &root]), [(0, 2), (1, 2)]);
+    }
+}
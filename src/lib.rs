@@ -1,11 +1,24 @@
 #![forbid(unsafe_code)]
 #![warn(clippy::pedantic, clippy::nursery, clippy::cargo)]
 
+mod accounts;
 mod analysis;
+mod bytes;
 mod data;
+mod filter;
+mod inputs;
+mod mounts;
 pub mod output;
+mod timestamp;
 
+pub use analysis::Options;
 #[cfg(feature = "spectrum-scale")]
 pub use analysis::spectrum_scale::run as spectrum_scale;
 pub use analysis::universal::run as universal;
-pub use data::Data;
+pub use analysis::universal::{Checkpoint, HardLinks, Symlinks};
+pub use bytes::parse_bytes;
+pub use data::{Amount, Data, Key, Skipped, Time, TopFile};
+pub use filter::Filter;
+pub use inputs::{NestedInput, nested_inputs};
+pub use mounts::AtimeMode;
+pub use timestamp::{days_before, parse_timestamp};
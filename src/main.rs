@@ -8,7 +8,7 @@ use std::collections::HashMap;
 use std::io::{self, IsTerminal, Read};
 
 use anyhow::{Context, Result};
-use stor_age::Data;
+use stor_age::{Checkpoint, Data, HardLinks, NestedInput};
 
 use crate::cli::Output;
 use crate::config::Config;
@@ -16,7 +16,7 @@ use crate::config::Config;
 fn main() -> Result<()> {
     let stdin_terminal = std::io::stdin().is_terminal();
     let args = cli::build(stdin_terminal).get_matches();
-    let config = Config::from_args(&args);
+    let mut config = Config::from_args(&args)?;
 
     if config.debug {
         env_logger::Builder::default()
@@ -30,11 +30,17 @@ fn main() -> Result<()> {
         env_logger::init();
     }
 
+    let checkpoint = checkpoint(&config)?;
+
+    if let Some(checkpoint) = &checkpoint {
+        config.options.reference_time = Some(checkpoint.reference_time());
+    }
+
     log::debug!("{config:#?}");
 
     if let Some(dirs) = args.get_many::<String>("dir") {
         let dirs: Vec<&str> = dirs.map(String::as_str).collect();
-        run(&dirs, &config);
+        run(&dirs, &config, checkpoint.as_ref());
     } else {
         let mut dirs = String::new();
 
@@ -44,57 +50,173 @@ fn main() -> Result<()> {
 
         let dirs: Vec<&str> = dirs.lines().collect();
 
-        run(&dirs, &config);
+        run(&dirs, &config, checkpoint.as_ref());
     }
 
     Ok(())
 }
 
-pub fn run(dirs: &[&str], config: &Config) {
+/// Warns about input directories inside other ones and skips them, if
+/// requested.
+fn dedupe<'a>(dirs: &[&'a str], config: &Config) -> Vec<&'a str> {
+    let nested = stor_age::nested_inputs(dirs);
+
+    for NestedInput { dir, parent } in &nested {
+        let (dir, parent) = (dirs[*dir], dirs[*parent]);
+
+        if config.dedupe_inputs {
+            log::warn!("skipping {dir}, it is inside {parent}");
+        } else {
+            log::warn!("scanning {dir} twice, it is inside {parent}");
+        }
+    }
+
+    if !config.dedupe_inputs {
+        return dirs.to_vec();
+    }
+
+    dirs.iter()
+        .enumerate()
+        .filter(|(i, _)| !nested.iter().any(|nested| nested.dir == *i))
+        .map(|(_, dir)| *dir)
+        .collect()
+}
+
+/// Returns the checkpoint to save progress to, resumed if requested.
+fn checkpoint(config: &Config) -> Result<Option<Checkpoint>> {
+    let Some(path) = &config.checkpoint else {
+        return Ok(None);
+    };
+
+    let interval = config.checkpoint_interval;
+
+    let checkpoint = if config.resume {
+        Checkpoint::resume(path, interval, &config.options)?
+    } else {
+        Checkpoint::new(path, interval, &config.options)
+    };
+
+    Ok(Some(checkpoint))
+}
+
+pub fn run(dirs: &[&str], config: &Config, checkpoint: Option<&Checkpoint>) {
+    let dirs = dedupe(dirs, config);
+
     let mut results: HashMap<&str, Data> = HashMap::new();
 
-    for dir in dirs {
+    let hard_links =
+        checkpoint.map_or_else(HardLinks::default, Checkpoint::hard_links);
+
+    let mut failed = false;
+
+    for dir in &dirs {
+        if let Some(acc) = checkpoint.and_then(|c| c.finished(dir)) {
+            if config.progress {
+                log::info!("already finished {dir}");
+            }
+
+            results.insert(dir, acc);
+            continue;
+        }
+
         if config.progress {
             log::info!("analyzing {dir}");
         }
 
-        let result = run_conditional(dir, config);
+        let result = run_conditional(dir, config, &hard_links, checkpoint);
 
         match result {
             Ok(acc) => {
+                for (kind, count) in acc.get_errors() {
+                    log::warn!("{dir}: skipped {count} entries: {kind}");
+                }
+
+                if let Some(checkpoint) = checkpoint {
+                    if let Err(error) = checkpoint.finish(dir, &acc) {
+                        log::error!("{error:#}");
+                    }
+                }
+
                 results.insert(dir, acc);
             }
 
             Err(error) => {
                 log::error!("skipping {dir}: {error}");
+                failed = true;
             }
         }
     }
 
+    // keep the checkpoint to resume from unless every input directory
+    // is finished
+    if let Some(checkpoint) = checkpoint {
+        let result = if failed {
+            checkpoint.save()
+        } else {
+            checkpoint.remove()
+        };
+
+        if let Err(error) = result {
+            log::error!("{error:#}");
+        }
+    }
+
     match config.output {
-        Output::Prometheus => stor_age::output::prometheus(&results),
-        Output::Oneline => stor_age::output::oneline(&results),
+        Output::Prometheus => {
+            stor_age::output::prometheus(
+                &results,
+                config.size,
+                &config.options,
+            );
+        }
+        Output::Oneline => {
+            stor_age::output::oneline(
+                &results,
+                config.size,
+                config.counts,
+                &config.options,
+            );
+        }
         #[cfg(feature = "table")]
-        Output::Table => stor_age::output::table(&results),
+        Output::Table => {
+            stor_age::output::table(
+                &results,
+                config.size,
+                config.counts,
+                &config.options,
+            );
+        }
     }
 }
 
 #[cfg(not(feature = "spectrum-scale"))]
-fn run_conditional(dir: &str, config: &Config) -> Result<Data> {
-    stor_age::universal(dir, &config.ages_in_days, config.one_file_system)
+fn run_conditional(
+    dir: &str,
+    config: &Config,
+    hard_links: &HardLinks,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<Data> {
+    stor_age::universal(dir, &config.options, hard_links, checkpoint)
 }
 
 #[cfg(feature = "spectrum-scale")]
-fn run_conditional(dir: &str, config: &Config) -> Result<Data> {
+fn run_conditional(
+    dir: &str,
+    config: &Config,
+    hard_links: &HardLinks,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<Data> {
     if config.spectrum_scale {
         stor_age::spectrum_scale(
             dir,
-            &config.ages_in_days,
+            &config.options,
             config.spectrum_scale_nodes.as_deref(),
+            config.spectrum_scale_thread_level,
+            config.spectrum_scale_dir_thread_level,
             config.spectrum_scale_local_work_dir.as_deref(),
             config.spectrum_scale_global_work_dir.as_deref(),
         )
     } else {
-        stor_age::universal(dir, &config.ages_in_days, config.one_file_system)
+        stor_age::universal(dir, &config.options, hard_links, checkpoint)
     }
 }
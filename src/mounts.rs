@@ -0,0 +1,285 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How access times are updated, according to the mount options.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtimeMode {
+    /// Access times are updated on every access.
+    Strict,
+
+    /// Access times are updated only if they are older than the modification
+    /// or status change time, or at most once a day.
+    Relatime,
+
+    /// Access times are never updated.
+    Noatime,
+}
+
+impl AtimeMode {
+    /// Returns the name of the mount option.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Strict => "strictatime",
+            Self::Relatime => "relatime",
+            Self::Noatime => "noatime",
+        }
+    }
+
+    /// Returns `true` if access times are updated on every access.
+    #[must_use]
+    pub const fn is_reliable(self) -> bool {
+        matches!(self, Self::Strict)
+    }
+}
+
+impl FromStr for AtimeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strictatime" => Ok(Self::Strict),
+            "relatime" => Ok(Self::Relatime),
+            "noatime" => Ok(Self::Noatime),
+            _ => Err(String::from("invalid atime mode")),
+        }
+    }
+}
+
+/// Mounted file system, see `proc_pid_mountinfo(5)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mount {
+    /// ID of the mounted device, as in `st_dev` of files on it.
+    pub device: u64,
+
+    /// Directory of the file system that is mounted, e.g. `/` unless only a
+    /// part of it is bind-mounted.
+    pub root: PathBuf,
+
+    /// Mount point.
+    pub target: PathBuf,
+
+    /// Per-mount options, e.g. `noatime`.
+    pub options: Vec<String>,
+
+    /// File system type, e.g. `ext4`.
+    pub fs_type: String,
+}
+
+impl Mount {
+    /// Returns how access times are updated on this mount.
+    #[must_use]
+    pub fn atime_mode(&self) -> AtimeMode {
+        let option = |name: &str| self.options.iter().any(|o| o == name);
+
+        if option("noatime") {
+            AtimeMode::Noatime
+        } else if option("relatime") {
+            AtimeMode::Relatime
+        } else {
+            AtimeMode::Strict
+        }
+    }
+}
+
+/// Returns the mounted file systems of this process, or none if they are not
+/// available, e.g. on other platforms than Linux.
+pub fn mounts() -> Vec<Mount> {
+    fs::read_to_string("/proc/self/mountinfo")
+        .map(|content| parse(&content))
+        .unwrap_or_default()
+}
+
+/// Returns the mount points of the visible mounts that have one of the file
+/// system types or are one of the mount points.
+pub fn excluded(
+    mounts: Vec<Mount>,
+    fs_types: &[String],
+    targets: &[PathBuf],
+) -> HashSet<PathBuf> {
+    let mut visible = HashMap::new();
+
+    // later mounts on the same mount point hide earlier ones
+    for mount in mounts {
+        visible.insert(mount.target.clone(), mount);
+    }
+
+    visible
+        .into_values()
+        .filter(|mount| {
+            fs_types.contains(&mount.fs_type)
+                || targets.contains(&mount.target)
+        })
+        .map(|mount| mount.target)
+        .collect()
+}
+
+/// Returns the mount point of each device. Of several mounts of a device,
+/// the first one of the whole file system is preferred over bind mounts of
+/// parts of it.
+pub fn mount_points(mounts: Vec<Mount>) -> HashMap<u64, PathBuf> {
+    let mut devices = HashMap::new();
+
+    for mount in mounts {
+        match devices.entry(mount.device) {
+            Entry::Vacant(entry) => {
+                entry.insert(mount);
+            }
+
+            Entry::Occupied(mut entry) => {
+                let whole = |mount: &Mount| mount.root == Path::new("/");
+
+                if !whole(entry.get()) && whole(&mount) {
+                    entry.insert(mount);
+                }
+            }
+        }
+    }
+
+    devices
+        .into_iter()
+        .map(|(device, mount)| (device, mount.target))
+        .collect()
+}
+
+/// Returns the mount containing the path, i.e. the one with the longest
+/// mount point. Of mounts on the same mount point, the last one is visible.
+pub fn containing<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.target))
+        .max_by_key(|mount| mount.target.components().count())
+}
+
+/// Parses the content of `/proc/self/mountinfo`, ignoring invalid lines.
+fn parse(content: &str) -> Vec<Mount> {
+    content.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<Mount> {
+    let mut fields = line.split(' ');
+
+    let device = fields.nth(2)?;
+    let root = fields.next()?;
+    let target = fields.next()?;
+    let options = fields.next()?;
+
+    // optional fields are terminated by a single hyphen
+    let fs_type = fields
+        .find(|field| *field == "-")
+        .and_then(|_| fields.next())?;
+
+    let (major, minor) = device.split_once(':')?;
+
+    Some(Mount {
+        device: makedev(major.parse().ok()?, minor.parse().ok()?),
+        root: PathBuf::from(unescape(root)),
+        target: PathBuf::from(unescape(target)),
+        options: options.split(',').map(String::from).collect(),
+        fs_type: unescape(fs_type),
+    })
+}
+
+/// Returns the device ID of major and minor numbers, encoded like glibc.
+const fn makedev(major: u64, minor: u64) -> u64 {
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+/// Replaces octal escapes like `\040` for spaces with the character.
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+
+    while let Some(i) = rest.find('\\') {
+        unescaped.push_str(&rest[..i]);
+
+        let escape = rest.get(i + 1..i + 4);
+        let byte = escape.and_then(|octal| u8::from_str_radix(octal, 8).ok());
+
+        if let Some(byte) = byte {
+            unescaped.push(char::from(byte));
+            rest = &rest[i + 4..];
+        } else {
+            unescaped.push('\\');
+            rest = &rest[i + 1..];
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+23 22 8:2 / /data rw,nosuid,noatime shared:2 master:1 - xfs /dev/sda2 rw
+24 23 0:40 / /data/my\\040project rw - nfs server:/export rw,vers=4.2
+25 22 0:41 / /scratch rw,noatime - tmpfs tmpfs rw
+26 22 0:42 / /scratch rw - tmpfs tmpfs rw
+27 22 8:2 /projects /projects rw - xfs /dev/sda2 rw
+28 22 259:65536 / /nvme rw - ext4 /dev/nvme0n1p1 rw
+";
+
+    #[test]
+    fn mountinfo() {
+        let mounts = parse(MOUNTINFO);
+
+        assert_eq!(mounts.len(), 7);
+        assert_eq!(mounts[1].fs_type, "xfs");
+        assert_eq!(mounts[2].target, Path::new("/data/my project"));
+
+        let mode = |path: &str| {
+            containing(&mounts, Path::new(path)).map(Mount::atime_mode)
+        };
+
+        assert_eq!(mode("/home/user"), Some(AtimeMode::Relatime));
+        assert_eq!(mode("/data/other"), Some(AtimeMode::Noatime));
+        assert_eq!(mode("/database"), Some(AtimeMode::Relatime));
+        assert_eq!(mode("/data/my project/x"), Some(AtimeMode::Strict));
+        assert_eq!(mode("/scratch"), Some(AtimeMode::Strict));
+    }
+
+    #[test]
+    fn exclude() {
+        let fs_types = [String::from("tmpfs"), String::from("xfs")];
+        let targets = [PathBuf::from("/data/my project/")];
+
+        let excluded = excluded(parse(MOUNTINFO), &fs_types, &targets);
+
+        assert_eq!(
+            excluded,
+            HashSet::from([
+                PathBuf::from("/data"),
+                PathBuf::from("/data/my project"),
+                PathBuf::from("/projects"),
+                PathBuf::from("/scratch"),
+            ])
+        );
+    }
+
+    #[test]
+    fn devices() {
+        let mount_points = mount_points(parse(MOUNTINFO));
+
+        assert_eq!(mount_points.len(), 6);
+        assert_eq!(mount_points[&0x0801], Path::new("/"));
+        assert_eq!(mount_points[&0x0802], Path::new("/data"));
+        assert_eq!(mount_points[&0x0029], Path::new("/scratch"));
+        assert_eq!(mount_points[&0x1001_0300], Path::new("/nvme"));
+    }
+
+    #[test]
+    fn unescaping() {
+        assert_eq!(unescape(r"a\040b\134c"), r"a b\c");
+        assert_eq!(unescape(r"a\b\04"), r"a\b\04");
+    }
+}
@@ -1,6 +1,17 @@
 mod oneline;
 mod prometheus;
 
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bytes::format_bytes;
+use crate::{Data, Key, Options, Time, accounts, mounts};
+
 pub use oneline::show as oneline;
 pub use prometheus::show as prometheus;
 
@@ -9,3 +20,314 @@ mod table;
 
 #[cfg(feature = "table")]
 pub use table::show as table;
+
+/// Size dimensions to report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Size {
+    /// Apparent size, i.e. the length of the files.
+    #[default]
+    Apparent,
+
+    /// Allocated size, i.e. the blocks used on the storage.
+    Allocated,
+
+    /// Both apparent and allocated size.
+    Both,
+}
+
+impl Size {
+    /// Returns `true` if apparent size should be reported.
+    #[must_use]
+    pub const fn apparent(self) -> bool {
+        matches!(self, Self::Apparent | Self::Both)
+    }
+
+    /// Returns `true` if allocated size should be reported.
+    #[must_use]
+    pub const fn allocated(self) -> bool {
+        matches!(self, Self::Allocated | Self::Both)
+    }
+}
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "apparent" => Ok(Self::Apparent),
+            "allocated" => Ok(Self::Allocated),
+            "both" => Ok(Self::Both),
+            _ => Err(String::from("invalid size")),
+        }
+    }
+}
+
+/// Returns the capitalized name of the time dimension.
+const fn title(time: Time) -> &'static str {
+    match time {
+        Time::Accessed => "Accessed",
+        Time::Modified => "Modified",
+        Time::Changed => "Changed",
+        Time::Created => "Created",
+    }
+}
+
+/// Breakdown of a directory, which is reported as an additional label or
+/// column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Label {
+    /// File owner.
+    User,
+
+    /// File group.
+    Group,
+
+    /// File extension.
+    Extension,
+
+    /// File size bucket.
+    Size,
+
+    /// Mount point of the device.
+    Device,
+}
+
+impl Label {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Group => "group",
+            Self::Extension => "extension",
+            Self::Size => "size",
+            Self::Device => "device",
+        }
+    }
+
+    #[cfg(feature = "table")]
+    const fn title(self) -> &'static str {
+        match self {
+            Self::User => "User",
+            Self::Group => "Group",
+            Self::Extension => "Extension",
+            Self::Size => "Size",
+            Self::Device => "Device",
+        }
+    }
+}
+
+/// Input directory or one of its subdirectories to report, possibly broken
+/// down further, e.g. by user.
+struct Row<'a> {
+    /// Input directory.
+    dir: &'a str,
+
+    /// Path of the subdirectory, which equals `dir` for the input directory.
+    path: String,
+
+    /// Depth below the input directory, if a breakdown by subdirectory was
+    /// requested.
+    depth: Option<usize>,
+
+    /// Breakdown this row belongs to, e.g. the name of the user.
+    labels: Vec<(Label, String)>,
+
+    data: &'a Data,
+}
+
+/// Returns the rows to report, i.e. each input directory followed by its
+/// subdirectories, if any. With a breakdown, e.g. by user, there is a row for
+/// each user instead of each directory.
+fn rows<'a, S: BuildHasher>(data: &'a HashMap<&str, Data, S>) -> Vec<Row<'a>> {
+    let names = Names::default();
+    let mut rows = vec![];
+
+    for (dir, data) in data {
+        let subdirectories = data.get_subdirectories();
+        let breakdown = data.get_breakdown().is_some();
+
+        let directories = iter::once((Path::new(""), data)).chain(
+            subdirectories
+                .into_iter()
+                .flatten()
+                .map(|(relative, data)| (relative.as_path(), data)),
+        );
+
+        for (relative, data) in directories {
+            let path = if relative.as_os_str().is_empty() {
+                (*dir).to_owned()
+            } else {
+                Path::new(dir).join(relative).display().to_string()
+            };
+
+            let depth = subdirectories.map(|_| relative.components().count());
+
+            if !breakdown {
+                rows.push(Row {
+                    dir,
+                    path,
+                    depth,
+                    labels: vec![],
+                    data,
+                });
+
+                continue;
+            }
+
+            for (key, data) in data.get_breakdown().into_iter().flatten() {
+                rows.push(Row {
+                    dir,
+                    path: path.clone(),
+                    depth,
+                    labels: vec![names.label(key)],
+                    data,
+                });
+            }
+        }
+    }
+
+    rows
+}
+
+/// User and group names and mount points, which are read only if needed.
+#[derive(Default)]
+struct Names {
+    users: OnceCell<HashMap<u32, String>>,
+    groups: OnceCell<HashMap<u32, String>>,
+    devices: OnceCell<HashMap<u64, PathBuf>>,
+}
+
+impl Names {
+    /// Returns the label of a breakdown key, resolving IDs to names and
+    /// devices to mount points. Unknown IDs are shown as numbers.
+    fn label(&self, key: &Key) -> (Label, String) {
+        let (label, names, id) = match key {
+            Key::User(uid) => {
+                (Label::User, self.users.get_or_init(accounts::users), uid)
+            }
+
+            Key::Group(gid) => {
+                (Label::Group, self.groups.get_or_init(accounts::groups), gid)
+            }
+
+            Key::Extension(extension) if extension.is_empty() => {
+                return (Label::Extension, String::from("none"));
+            }
+
+            Key::Extension(extension) => {
+                return (Label::Extension, extension.clone());
+            }
+
+            Key::OtherExtensions => {
+                return (Label::Extension, String::from("other"));
+            }
+
+            Key::Size { max: Some(max), .. } => {
+                return (Label::Size, format!("<{}", format_bytes(*max)));
+            }
+
+            Key::Size { min, max: None } => {
+                return (Label::Size, format!(">={}", format_bytes(*min)));
+            }
+
+            Key::Device(device) => {
+                let devices = self
+                    .devices
+                    .get_or_init(|| mounts::mount_points(mounts::mounts()));
+
+                let name = devices.get(device).map_or_else(
+                    || device.to_string(),
+                    |target| target.display().to_string(),
+                );
+
+                return (Label::Device, name);
+            }
+        };
+
+        let name = names.get(id).cloned().unwrap_or_else(|| id.to_string());
+
+        (label, name)
+    }
+}
+
+/// Returns the labels used by any row, in order of first appearance.
+fn labels(rows: &[Row]) -> Vec<Label> {
+    let mut labels = vec![];
+
+    for row in rows {
+        for (label, _) in &row.labels {
+            if !labels.contains(label) {
+                labels.push(*label);
+            }
+        }
+    }
+
+    labels
+}
+
+/// Returns the value of `label` for the row, or an empty string.
+fn label_value<'a>(row: &'a Row, label: Label) -> &'a str {
+    row.labels
+        .iter()
+        .find(|(l, _)| *l == label)
+        .map_or("", |(_, value)| value.as_str())
+}
+
+/// Returns the ages of the data along with how to show them, i.e. the
+/// threshold or, with buckets, the age range, e.g. `30-90` or `90+` for the
+/// open-ended bucket.
+fn ages(data: &Data) -> Vec<(u64, String)> {
+    let ages = data.get_ages();
+
+    if !data.has_buckets() {
+        return ages
+            .into_iter()
+            .map(|age| (*age, age.to_string()))
+            .collect();
+    }
+
+    let next = ages.iter().skip(1).map(Some).chain(iter::once(None));
+
+    ages.iter()
+        .zip(next)
+        .map(|(age, next)| {
+            let range = next.map_or_else(
+                || format!("{age}+"),
+                |next| format!("{age}-{next}"),
+            );
+
+            (**age, range)
+        })
+        .collect()
+}
+
+/// Returns the seconds since the Unix epoch, or an empty string if the time
+/// is unknown.
+fn seconds(time: Option<SystemTime>) -> String {
+    time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs().to_string())
+        .unwrap_or_default()
+}
+
+/// Returns the settings that limit which files are counted, to record them
+/// in the report, as name and value.
+fn settings(options: &Options) -> Vec<(&'static str, String)> {
+    let mut settings = vec![];
+
+    if let Some(min) = options.min_depth {
+        settings.push(("min_depth", min.to_string()));
+    }
+
+    if let Some(max) = options.max_depth {
+        settings.push(("max_depth", max.to_string()));
+    }
+
+    if let Some(min) = options.min_size {
+        settings.push(("min_size", min.to_string()));
+    }
+
+    if let Some(max) = options.max_size {
+        settings.push(("max_size", max.to_string()));
+    }
+
+    settings
+}
@@ -1,20 +1,93 @@
 use std::collections::HashMap;
 use std::hash::BuildHasher;
 
-use crate::Data;
+use crate::output::{
+    Size, ages, label_value, labels, rows, seconds, settings,
+};
+use crate::{Data, Options};
 
-pub fn show<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
-    for (dir, data) in data {
-        let t_b = data.get_total_bytes();
-        let t_f = data.get_total_files();
+pub fn show<S: BuildHasher>(
+    data: &HashMap<&str, Data, S>,
+    size: Size,
+    counts: bool,
+    options: &Options,
+) {
+    let times = &options.times;
+    let rows = rows(data);
+    let labels = labels(&rows);
 
-        for age in data.get_ages() {
-            let a_b = data.get_accessed_bytes(*age).unwrap_or_default();
-            let m_b = data.get_modified_bytes(*age).unwrap_or_default();
-            let a_f = data.get_accessed_files(*age).unwrap_or_default();
-            let m_f = data.get_modified_files(*age).unwrap_or_default();
+    for (name, value) in settings(options) {
+        println!("#setting:{name}:{value}");
+    }
+
+    for row in rows {
+        let data = row.data;
+        let total = data.get_total();
+        let l = data.get_hard_links();
+        let s_f = data.get_followed_symlinks();
+        let s_s = data.get_skipped_symlinks();
+        let skipped = data.get_skipped();
+
+        for (age, range) in ages(data) {
+            let amounts: Vec<_> = times
+                .iter()
+                .map(|time| data.get(age, *time).unwrap_or_default())
+                .collect();
+
+            let mut fields = vec![];
+
+            if size.apparent() {
+                fields.push(total.bytes);
+                fields.extend(amounts.iter().map(|amount| amount.bytes));
+            }
+
+            if size.allocated() {
+                fields.push(total.allocated);
+                fields.extend(amounts.iter().map(|amount| amount.allocated));
+            }
+
+            fields.push(total.files);
+            fields.extend(amounts.iter().map(|amount| amount.files));
+
+            if counts {
+                fields.extend([l, s_f, s_s]);
+                fields.extend([
+                    skipped.permission_denied,
+                    skipped.other_file_system,
+                    skipped.special_files,
+                ]);
+            }
+
+            print!("{range}:");
+
+            for field in fields {
+                print!("{field}:");
+            }
+
+            for label in &labels {
+                print!("{}:", label_value(&row, *label));
+            }
+
+            if let Some(depth) = row.depth {
+                print!("{depth}:");
+            }
+
+            println!("{}", row.path);
+        }
+    }
 
-            println!("{age}:{t_b}:{a_b}:{m_b}:{t_f}:{a_f}:{m_f}:{dir}");
+    for data in data.values() {
+        for (age, files) in data.get_top() {
+            for (rank, file) in files.iter().enumerate() {
+                println!(
+                    "#top:{age}:{}:{}:{}:{}:{}",
+                    rank + 1,
+                    file.bytes,
+                    seconds(file.accessed),
+                    seconds(file.modified),
+                    file.path.display()
+                );
+            }
         }
     }
 }
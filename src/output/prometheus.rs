@@ -1,96 +1,288 @@
 use std::collections::HashMap;
 use std::hash::BuildHasher;
 
-use crate::Data;
+use crate::output::{Row, Size, ages, rows, seconds, settings, title};
+use crate::{Amount, Data, Options, Time, TopFile};
 
-pub fn show<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
-    show_bytes(data);
+pub fn show<S: BuildHasher>(
+    data: &HashMap<&str, Data, S>,
+    size: Size,
+    options: &Options,
+) {
+    let times = &options.times;
+    let rows = rows(data);
+
+    show_settings(options);
+
+    if size.apparent() {
+        show_amount(&rows, times, "bytes", "size in bytes", |amount| {
+            amount.bytes
+        });
+        println!();
+    }
+
+    if size.allocated() {
+        show_amount(
+            &rows,
+            times,
+            "allocated_bytes",
+            "allocated size in bytes",
+            |amount| amount.allocated,
+        );
+        println!();
+    }
+
+    show_amount(&rows, times, "files", "number of files", |amount| {
+        amount.files
+    });
+    println!();
+    show_links(&rows);
+    println!();
+    show_symlinks(&rows);
     println!();
-    show_files(data);
+    show_skipped(&rows);
+    println!();
+    show_errors(data);
+
+    if data.values().any(|data| data.get_atime_mode().is_some()) {
+        println!();
+        show_atime_mode(data);
+    }
+
+    if data.values().any(|data| !data.get_top().is_empty()) {
+        println!();
+        show_top(data, "bytes", "Size in bytes", |file| {
+            file.bytes.to_string()
+        });
+        println!();
+        show_top(data, "accessed_seconds", "Access time", |file| {
+            seconds(file.accessed)
+        });
+        println!();
+        show_top(data, "modified_seconds", "Modification time", |file| {
+            seconds(file.modified)
+        });
+    }
 }
 
-fn show_bytes<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
-    println!("# HELP stor_age_bytes_total Total size in bytes.");
-    println!("# TYPE stor_age_bytes_total gauge");
+fn show_settings(options: &Options) {
+    let settings = settings(options);
 
-    for (dir, data) in data {
+    if settings.is_empty() {
+        return;
+    }
+
+    let labels: Vec<String> = settings
+        .iter()
+        .map(|(name, value)| format!("{name}=\"{}\"", escape(value)))
+        .collect();
+
+    println!(
+        "# HELP stor_age_info Settings limiting which files are counted."
+    );
+    println!("# TYPE stor_age_info gauge");
+    println!("stor_age_info{{{}}} 1", labels.join(","));
+    println!();
+}
+
+fn show_amount(
+    rows: &[Row],
+    times: &[Time],
+    name: &str,
+    help: &str,
+    value: fn(Amount) -> u64,
+) {
+    println!("# HELP stor_age_{name}_total Total {help}.");
+    println!("# TYPE stor_age_{name}_total gauge");
+
+    for row in rows {
         println!(
-            "stor_age_bytes_total{{dir=\"{}\"}} {}",
-            dir,
-            data.get_total_bytes()
+            "stor_age_{}_total{{{}}} {}",
+            name,
+            labels(row),
+            value(row.data.get_total())
         );
     }
 
-    println!();
-    println!("# HELP stor_age_bytes_accessed Accessed size in bytes.");
-    println!("# TYPE stor_age_bytes_accessed gauge");
+    for time in times {
+        let metric = format!("stor_age_{name}_{}", time.name());
 
-    for (dir, data) in data {
-        for age in data.get_ages() {
-            println!(
-                "stor_age_bytes_accessed{{dir=\"{}\",age=\"{}\"}} {}",
-                dir,
-                age,
-                data.get_accessed_bytes(*age).unwrap()
-            );
+        println!();
+        println!("# HELP {metric} {} {help}.", title(*time));
+        println!("# TYPE {metric} gauge");
+
+        for row in rows {
+            for (age, range) in ages(row.data) {
+                println!(
+                    "{}{{{},age=\"{}\"}} {}",
+                    metric,
+                    labels(row),
+                    range,
+                    value(row.data.get(age, *time).unwrap())
+                );
+            }
         }
     }
+}
 
-    println!();
-    println!("# HELP stor_age_bytes_modified Modified size in bytes.");
-    println!("# TYPE stor_age_bytes_modified gauge");
+fn show_links(rows: &[Row]) {
+    println!(
+        "# HELP stor_age_files_links \
+         Additional hard links to already counted files."
+    );
+    println!("# TYPE stor_age_files_links gauge");
 
-    for (dir, data) in data {
-        for age in data.get_ages() {
-            println!(
-                "stor_age_bytes_modified{{dir=\"{}\",age=\"{}\"}} {}",
-                dir,
-                age,
-                data.get_modified_bytes(*age).unwrap()
-            );
-        }
+    for row in rows {
+        println!(
+            "stor_age_files_links{{{}}} {}",
+            labels(row),
+            row.data.get_hard_links()
+        );
     }
 }
 
-fn show_files<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
-    println!("# HELP stor_age_files_total Total number of files.");
-    println!("# TYPE stor_age_files_total gauge");
+fn show_symlinks(rows: &[Row]) {
+    println!("# HELP stor_age_symlinks_followed Followed symbolic links.");
+    println!("# TYPE stor_age_symlinks_followed gauge");
 
-    for (dir, data) in data {
+    for row in rows {
         println!(
-            "stor_age_files_total{{dir=\"{}\"}} {}",
-            dir,
-            data.get_total_files()
+            "stor_age_symlinks_followed{{{}}} {}",
+            labels(row),
+            row.data.get_followed_symlinks()
         );
     }
 
     println!();
-    println!("# HELP stor_age_files_accessed Accessed number of files.");
-    println!("# TYPE stor_age_files_accessed gauge");
+    println!("# HELP stor_age_symlinks_skipped Skipped symbolic links.");
+    println!("# TYPE stor_age_symlinks_skipped gauge");
+
+    for row in rows {
+        println!(
+            "stor_age_symlinks_skipped{{{}}} {}",
+            labels(row),
+            row.data.get_skipped_symlinks()
+        );
+    }
+}
+
+fn show_skipped(rows: &[Row]) {
+    println!(
+        "# HELP stor_age_skipped_total Entries skipped during traversal."
+    );
+    println!("# TYPE stor_age_skipped_total gauge");
+
+    for row in rows {
+        let skipped = row.data.get_skipped();
+
+        let reasons = [
+            ("permission_denied", skipped.permission_denied),
+            ("other_file_system", skipped.other_file_system),
+            ("symlink", row.data.get_skipped_symlinks()),
+            ("special_file", skipped.special_files),
+        ];
+
+        for (reason, count) in reasons {
+            println!(
+                "stor_age_skipped_total{{{},reason=\"{reason}\"}} {count}",
+                labels(row),
+            );
+        }
+    }
+}
+
+fn show_errors<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
+    println!(
+        "# HELP stor_age_errors Errors of single entries skipped during \
+         traversal."
+    );
+    println!("# TYPE stor_age_errors gauge");
 
     for (dir, data) in data {
-        for age in data.get_ages() {
+        for (kind, count) in data.get_errors() {
             println!(
-                "stor_age_files_accessed{{dir=\"{}\",age=\"{}\"}} {}",
-                dir,
-                age,
-                data.get_accessed_files(*age).unwrap()
+                "stor_age_errors{{dir=\"{}\",kind=\"{}\"}} {}",
+                escape(dir),
+                escape(kind),
+                count
             );
         }
     }
+}
 
-    println!();
-    println!("# HELP stor_age_files_modified Modified number of files.");
-    println!("# TYPE stor_age_files_modified gauge");
+fn show_atime_mode<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
+    println!(
+        "# HELP stor_age_atime_mode How access times are updated according to \
+         the mount options, access ages are reliable only with strictatime."
+    );
+    println!("# TYPE stor_age_atime_mode gauge");
 
     for (dir, data) in data {
-        for age in data.get_ages() {
+        if let Some(mode) = data.get_atime_mode() {
             println!(
-                "stor_age_files_modified{{dir=\"{}\",age=\"{}\"}} {}",
-                dir,
-                age,
-                data.get_modified_files(*age).unwrap()
+                "stor_age_atime_mode{{dir=\"{}\",mode=\"{}\"}} 1",
+                escape(dir),
+                mode.name()
             );
         }
     }
 }
+
+fn show_top<S: BuildHasher>(
+    data: &HashMap<&str, Data, S>,
+    name: &str,
+    help: &str,
+    value: fn(&TopFile) -> String,
+) {
+    let metric = format!("stor_age_top_{name}");
+
+    println!(
+        "# HELP {metric} {help} of the largest files past the age threshold."
+    );
+    println!("# TYPE {metric} gauge");
+
+    for (dir, data) in data {
+        for (age, files) in data.get_top() {
+            for (rank, file) in files.iter().enumerate() {
+                let value = value(file);
+
+                // unknown times are omitted
+                if value.is_empty() {
+                    continue;
+                }
+
+                println!(
+                    "{metric}{{dir=\"{}\",age=\"{age}\",rank=\"{}\",\
+                     path=\"{}\"}} {value}",
+                    escape(dir),
+                    rank + 1,
+                    escape(&file.path.display().to_string()),
+                );
+            }
+        }
+    }
+}
+
+/// Returns the labels identifying the row, without braces.
+fn labels(row: &Row) -> String {
+    let mut labels = vec![format!("dir=\"{}\"", escape(row.dir))];
+
+    if let Some(depth) = row.depth {
+        labels.push(format!("path=\"{}\"", escape(&row.path)));
+        labels.push(format!("depth=\"{depth}\""));
+    }
+
+    for (label, value) in &row.labels {
+        labels.push(format!("{}=\"{}\"", label.name(), escape(value)));
+    }
+
+    labels.join(",")
+}
+
+/// Escapes a label value.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
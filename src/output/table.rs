@@ -1,114 +1,293 @@
 use std::collections::HashMap;
 use std::hash::BuildHasher;
+use std::time::SystemTime;
 
 use bytesize::ByteSize;
+use jiff::tz::TimeZone;
 use prettytable::{Row, Table, cell, format::FormatBuilder};
 use smooth::Smooth;
 
-use crate::Data;
+use crate::output::{
+    Label, Size, ages, label_value, labels, rows, settings, title,
+};
+use crate::timestamp::format_timestamp;
+use crate::{AtimeMode, Data, Options, Time};
 
-pub fn show<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
+pub fn show<S: BuildHasher>(
+    data: &HashMap<&str, Data, S>,
+    size: Size,
+    counts: bool,
+    options: &Options,
+) {
+    let times = &options.times;
     let mut table = Table::new();
     let format = FormatBuilder::new().column_separator(' ').build();
     table.set_format(format);
 
-    let mut titles = Row::empty();
-    titles.add_cell(cell!(bu->"Directory"));
-    titles.add_cell(cell!(bu->"Age"));
-    titles.add_cell(cell!(bu->"Bytes"));
-    titles.add_cell(cell!(bu->"Accessed"));
-    titles.add_cell(cell!(bu->"Percent"));
-    titles.add_cell(cell!(bu->"Modified"));
-    titles.add_cell(cell!(bu->"Percent"));
-    titles.add_cell(cell!(bu->"Files"));
-    titles.add_cell(cell!(bu->"Accessed"));
-    titles.add_cell(cell!(bu->"Percent"));
-    titles.add_cell(cell!(bu->"Modified"));
-    titles.add_cell(cell!(bu->"Percent"));
-    table.set_titles(titles);
+    let rows = rows(data);
+    let depth = rows.iter().any(|row| row.depth.is_some());
+    let labels = labels(&rows);
 
-    for (dir, data) in data {
-        let total_bytes = data.get_total_bytes();
-        let total_files = data.get_total_files();
+    table.set_titles(titles(depth, &labels, size, counts, times));
+
+    for data_row in rows {
+        let data = data_row.data;
+        let total = data.get_total();
+        let hard_links = data.get_hard_links();
+        let followed_symlinks = data.get_followed_symlinks();
+        let skipped_symlinks = data.get_skipped_symlinks();
+        let skipped = data.get_skipped();
 
         let mut first = true;
 
-        for age in data.get_ages() {
+        for (age, range) in ages(data) {
             let mut row = Row::empty();
 
             if first {
-                row.add_cell(cell!(dir));
+                row.add_cell(cell!(data_row.path));
             } else {
                 row.add_cell(cell!(""));
             }
 
-            row.add_cell(cell!(r->age));
+            if depth {
+                match data_row.depth {
+                    Some(depth) if first => row.add_cell(cell!(r->depth)),
+                    _ => row.add_cell(cell!(r->"")),
+                }
+            }
 
-            if first {
-                row.add_cell(cell!(r->ByteSize(total_bytes).display().iec()));
-            } else {
-                row.add_cell(cell!(r->""));
+            for label in &labels {
+                if first {
+                    row.add_cell(cell!(label_value(&data_row, *label)));
+                } else {
+                    row.add_cell(cell!(""));
+                }
             }
 
-            let accessed_bytes =
-                data.get_accessed_bytes(*age).unwrap_or_default();
-            let modified_bytes =
-                data.get_modified_bytes(*age).unwrap_or_default();
+            row.add_cell(cell!(r->range));
 
-            let (accessed_bytes_percentage, modified_bytes_percentage) =
-                percentage(total_bytes, accessed_bytes, modified_bytes);
+            let amounts: Vec<_> = times
+                .iter()
+                .map(|time| data.get(age, *time).unwrap_or_default())
+                .collect();
 
-            let accessed_bytes = ByteSize(accessed_bytes).display().iec();
-            let modified_bytes = ByteSize(modified_bytes).display().iec();
+            if size.apparent() {
+                let bytes = amounts.iter().map(|amount| amount.bytes);
+                add_cells(&mut row, first, total.bytes, bytes, |bytes| {
+                    ByteSize(bytes).display().iec().to_string()
+                });
+            }
 
-            row.add_cell(cell!(r->accessed_bytes));
-            row.add_cell(cell!(r->format!("{accessed_bytes_percentage}%")));
+            if size.allocated() {
+                let bytes = amounts.iter().map(|amount| amount.allocated);
+                add_cells(&mut row, first, total.allocated, bytes, |bytes| {
+                    ByteSize(bytes).display().iec().to_string()
+                });
+            }
 
-            row.add_cell(cell!(r->modified_bytes));
-            row.add_cell(cell!(r->format!("{modified_bytes_percentage}%")));
+            let files = amounts.iter().map(|amount| amount.files);
+            add_cells(&mut row, first, total.files, files, |files| {
+                files.to_string()
+            });
 
-            if first {
-                row.add_cell(cell!(r->format!("{total_files}")));
-            } else {
-                row.add_cell(cell!(r->""));
+            if counts {
+                let counts = [
+                    hard_links,
+                    followed_symlinks,
+                    skipped_symlinks,
+                    skipped.permission_denied,
+                    skipped.other_file_system,
+                    skipped.special_files,
+                ];
+
+                for count in counts {
+                    if first {
+                        row.add_cell(cell!(r->count));
+                    } else {
+                        row.add_cell(cell!(r->""));
+                    }
+                }
             }
 
-            let accessed_files =
-                data.get_accessed_files(*age).unwrap_or_default();
-            let modified_files =
-                data.get_modified_files(*age).unwrap_or_default();
+            table.add_row(row);
+
+            first = false;
+        }
+    }
+
+    println!();
 
-            let (accessed_files_percentage, modified_files_percentage) =
-                percentage(total_files, accessed_files, modified_files);
+    let settings: Vec<String> = settings(options)
+        .into_iter()
+        .map(|(name, value)| format!("{}: {value}", name.replace('_', " ")))
+        .collect();
 
-            row.add_cell(cell!(r->accessed_files));
-            row.add_cell(cell!(r->format!("{accessed_files_percentage}%")));
+    if !settings.is_empty() {
+        println!("Counting only files with {}", settings.join(", "));
+        println!();
+    }
 
-            row.add_cell(cell!(r->modified_files));
-            row.add_cell(cell!(r->format!("{modified_files_percentage}%")));
+    if times.contains(&Time::Accessed) {
+        show_atime_warnings(data);
+    }
 
-            table.add_row(row);
+    table.printstd();
+    println!();
 
-            first = false;
+    if data.values().any(|data| !data.get_top().is_empty()) {
+        show_top(data);
+    }
+}
+
+/// Shows the largest files past each age threshold.
+fn show_top<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
+    let mut table = Table::new();
+    let format = FormatBuilder::new().column_separator(' ').build();
+    table.set_format(format);
+
+    let mut titles = Row::empty();
+    titles.add_cell(cell!(bu->"Age"));
+    titles.add_cell(cell!(bu->"Bytes"));
+    titles.add_cell(cell!(bu->"Accessed"));
+    titles.add_cell(cell!(bu->"Modified"));
+    titles.add_cell(cell!(bu->"File"));
+    table.set_titles(titles);
+
+    let time = |time: Option<SystemTime>| {
+        time.map(|time| format_timestamp(time, &TimeZone::UTC))
+            .unwrap_or_default()
+    };
+
+    for data in data.values() {
+        for (age, files) in data.get_top() {
+            for file in files {
+                let mut row = Row::empty();
+                row.add_cell(cell!(r->age));
+                row.add_cell(
+                    cell!(r->ByteSize(file.bytes).display().iec().to_string()),
+                );
+                row.add_cell(cell!(time(file.accessed)));
+                row.add_cell(cell!(time(file.modified)));
+                row.add_cell(cell!(file.path.display()));
+                table.add_row(row);
+            }
         }
     }
 
+    println!("Largest files past each age threshold (UTC):");
     println!();
     table.printstd();
     println!();
 }
 
-#[allow(clippy::cast_precision_loss)]
-fn percentage(total: u64, accessed: u64, modified: u64) -> (f64, f64) {
-    if total == 0 {
-        (0.0, 0.0)
+/// Warns about input directories on file systems that do not update access
+/// times on every access.
+fn show_atime_warnings<S: BuildHasher>(data: &HashMap<&str, Data, S>) {
+    let mut dirs: Vec<(&str, AtimeMode)> = data
+        .iter()
+        .filter_map(|(dir, data)| Some((*dir, data.get_atime_mode()?)))
+        .filter(|(_, mode)| !mode.is_reliable())
+        .collect();
+
+    if dirs.is_empty() {
+        return;
+    }
+
+    dirs.sort_unstable_by_key(|(dir, _)| *dir);
+
+    for (dir, mode) in dirs {
+        let consequence = match mode {
+            AtimeMode::Noatime => "access times are never updated",
+            _ => "access times are updated at most once a day",
+        };
+
+        println!(
+            "Warning: {dir} is mounted {}, {consequence}, access ages are not \
+             reliable",
+            mode.name()
+        );
+    }
+
+    println!();
+}
+
+fn titles(
+    depth: bool,
+    labels: &[Label],
+    size: Size,
+    counts: bool,
+    times: &[Time],
+) -> Row {
+    let mut titles = Row::empty();
+    titles.add_cell(cell!(bu->"Directory"));
+
+    if depth {
+        titles.add_cell(cell!(bu->"Depth"));
+    }
+
+    for label in labels {
+        titles.add_cell(cell!(bu->label.title()));
+    }
+
+    titles.add_cell(cell!(bu->"Age"));
+
+    if size.apparent() {
+        add_titles(&mut titles, "Bytes", times);
+    }
+
+    if size.allocated() {
+        add_titles(&mut titles, "Allocated", times);
+    }
+
+    add_titles(&mut titles, "Files", times);
+
+    if counts {
+        titles.add_cell(cell!(bu->"Links"));
+        titles.add_cell(cell!(bu->"Followed"));
+        titles.add_cell(cell!(bu->"Skipped"));
+        titles.add_cell(cell!(bu->"Denied"));
+        titles.add_cell(cell!(bu->"Other FS"));
+        titles.add_cell(cell!(bu->"Special"));
+    }
+
+    titles
+}
+
+fn add_titles(titles: &mut Row, total: &str, times: &[Time]) {
+    titles.add_cell(cell!(bu->total));
+
+    for time in times {
+        titles.add_cell(cell!(bu->title(*time)));
+        titles.add_cell(cell!(bu->"Percent"));
+    }
+}
+
+fn add_cells(
+    row: &mut Row,
+    first: bool,
+    total: u64,
+    values: impl Iterator<Item = u64>,
+    display: fn(u64) -> String,
+) {
+    if first {
+        row.add_cell(cell!(r->display(total)));
     } else {
-        let accessed_percentage =
-            ((accessed as f64) / (total as f64) * 100.0).round_to(2);
+        row.add_cell(cell!(r->""));
+    }
 
-        let modified_percentage =
-            ((modified as f64) / (total as f64) * 100.0).round_to(2);
+    for value in values {
+        let percentage = percentage(total, value);
 
-        (accessed_percentage, modified_percentage)
+        row.add_cell(cell!(r->display(value)));
+        row.add_cell(cell!(r->format!("{percentage}%")));
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percentage(total: u64, value: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        ((value as f64) / (total as f64) * 100.0).round_to(2)
     }
 }
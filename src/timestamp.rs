@@ -0,0 +1,117 @@
+use std::time::SystemTime;
+
+use anyhow::{Result, anyhow};
+use jiff::{SignedDuration, Timestamp};
+
+#[cfg(any(feature = "spectrum-scale", feature = "table"))]
+use jiff::tz::TimeZone;
+
+/// Parses a point in time, either in seconds since the Unix epoch, e.g.
+/// `1700000000`, or in RFC 3339 format, e.g. `2024-01-31T12:00:00Z` or
+/// `2024-01-31T13:00:00+01:00`.
+///
+/// # Errors
+///
+/// Returns an error if the timestamp is in neither format or out of range.
+pub fn parse_timestamp(s: &str) -> Result<SystemTime> {
+    let s = s.trim();
+
+    let timestamp = match s.parse::<i64>() {
+        Ok(seconds) => Timestamp::from_second(seconds)
+            .map_err(|_| anyhow!("timestamp out of range: {s}"))?,
+        Err(_) => s
+            .parse::<Timestamp>()
+            .map_err(|error| anyhow!("invalid timestamp: {s}: {error}"))?,
+    };
+
+    Ok(SystemTime::from(timestamp))
+}
+
+/// Returns the point in time the number of days before `time`.
+///
+/// # Errors
+///
+/// Returns an error if the point in time can not be represented, e.g. with
+/// an extreme reference time.
+pub fn days_before(time: SystemTime, days: u64) -> Result<SystemTime> {
+    let age = i64::try_from(days)
+        .ok()
+        .and_then(|days| days.checked_mul(60 * 60 * 24))
+        .map(SignedDuration::from_secs);
+
+    age.and_then(|age| Timestamp::try_from(time).ok()?.checked_sub(age).ok())
+        .map(SystemTime::from)
+        .ok_or_else(|| {
+            anyhow!("age of {days} days is out of range of the reference time")
+        })
+}
+
+/// Formats a point in time as civil time in `tz` as `YYYY-MM-DD HH:MM:SS`.
+#[cfg(any(feature = "spectrum-scale", feature = "table"))]
+pub fn format_timestamp(time: SystemTime, tz: &TimeZone) -> String {
+    Timestamp::try_from(time).map_or_else(
+        |_| String::from("out of range"),
+        |timestamp| {
+            timestamp
+                .to_zoned(tz.clone())
+                .strftime("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::UNIX_EPOCH;
+
+    fn seconds(s: &str) -> u64 {
+        parse_timestamp(s)
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(seconds("1700000000"), 1_700_000_000);
+        assert_eq!(seconds("1970-01-01T00:00:00Z"), 0);
+        assert_eq!(seconds("2023-11-14T22:13:20Z"), 1_700_000_000);
+        assert_eq!(seconds("2023-11-14 22:13:20.5z"), 1_700_000_000);
+        assert_eq!(seconds("2023-11-15T00:13:20+02:00"), 1_700_000_000);
+        assert_eq!(seconds("2024-02-29T00:00:00Z"), 1_709_164_800);
+
+        assert!(parse_timestamp("2023-02-29T00:00:00Z").is_err());
+        assert!(parse_timestamp("2023-11-14T22:13:20").is_err());
+        assert!(parse_timestamp("2023-11-14").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+        assert!(parse_timestamp("-9223372036854775000").is_err());
+    }
+
+    #[test]
+    fn before() {
+        let time = parse_timestamp("2024-03-01T00:00:00Z").unwrap();
+        let expected = parse_timestamp("2024-02-28T00:00:00Z").unwrap();
+        assert_eq!(days_before(time, 2).unwrap(), expected);
+
+        assert!(days_before(time, u64::MAX).is_err());
+        assert!(days_before(time, 10_000_000).is_err());
+    }
+
+    #[cfg(any(feature = "spectrum-scale", feature = "table"))]
+    #[test]
+    fn format() {
+        let utc = TimeZone::UTC;
+
+        let time = parse_timestamp("2023-11-15T00:13:20+02:00").unwrap();
+        assert_eq!(format_timestamp(time, &utc), "2023-11-14 22:13:20");
+
+        let time = parse_timestamp("-1").unwrap();
+        assert_eq!(format_timestamp(time, &utc), "1969-12-31 23:59:59");
+
+        let tz = TimeZone::fixed(jiff::tz::offset(2));
+        assert_eq!(format_timestamp(time, &tz), "1970-01-01 01:59:59");
+    }
+}
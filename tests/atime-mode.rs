@@ -0,0 +1,29 @@
+#![cfg(target_os = "linux")]
+
+use std::error::Error;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn atime_mode_metric() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "prometheus", "1", "--"])
+        .arg(dir.path());
+
+    let metric = format!(
+        "stor_age_atime_mode{{dir=\"{}\",mode=\"",
+        dir.path().display()
+    );
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(metric));
+
+    Ok(())
+}
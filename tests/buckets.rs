@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn buckets() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("a"), vec![0; 100])?;
+    fs::write(dir.path().join("b"), vec![0; 50])?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--buckets"])
+        .args(["2", "1", "--"])
+        .arg(dir.path());
+
+    let dir = dir.path().display();
+
+    let expected = format!(
+        "\
+0-1:150:150:150:2:2:2:{dir}
+1-2:150:0:0:2:0:0:{dir}
+2+:150:0:0:2:0:0:{dir}
+"
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
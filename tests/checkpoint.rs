@@ -0,0 +1,166 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+/// Creates an input directory with one file of one byte in each of `n`
+/// subdirectories.
+fn input(dir: &Path, n: usize) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fs::create_dir(dir)?;
+
+    let mut subdirectories = vec![];
+
+    for i in 0..n {
+        let sub = dir.join(i.to_string());
+        fs::create_dir(&sub)?;
+        fs::write(sub.join("file"), "x")?;
+        subdirectories.push(sub);
+    }
+
+    Ok(subdirectories)
+}
+
+#[test]
+fn resume_interrupted() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let work = tempdir()?;
+    let file = work.path().join("checkpoint");
+
+    let first = dir.path().join("first");
+    let second = dir.path().join("second");
+
+    input(&first, 2)?;
+    let subdirectories = input(&second, 10)?;
+
+    let command = |args: &[&str]| -> Result<Command, Box<dyn Error>> {
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        cmd.args(["--format", "oneline", "--checkpoint"])
+            .arg(&file)
+            .args(["--checkpoint-interval", "0", "--threads", "1"])
+            .args(["--max-dirs-per-second", "5"])
+            .args(args)
+            .args(["1", "--"])
+            .args([&first, &second]);
+
+        Ok(cmd)
+    };
+
+    let mut child = command(&[])?.stdout(Stdio::null()).spawn()?;
+
+    // interrupt once the first input directory and some subdirectories of
+    // the second are finished
+    let finished = format!("finished {}", first.display());
+    let current = format!("current {}", second.display());
+    let pending = |sub: &Path| format!("pending {}\n", sub.display());
+
+    let done = loop {
+        assert!(child.try_wait()?.is_none(), "interrupted too late");
+
+        let checkpoint = fs::read_to_string(&file).unwrap_or_default();
+
+        if checkpoint.contains(&finished)
+            && checkpoint.contains(&current)
+            && !checkpoint.contains(&pending(&second))
+        {
+            if let Some(done) = subdirectories
+                .iter()
+                .find(|sub| !checkpoint.contains(&pending(sub)))
+            {
+                break done;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    child.kill()?;
+    child.wait()?;
+
+    // files added to finished directories are not counted when resuming
+    fs::write(first.join("late"), "late")?;
+    fs::write(done.join("late"), "late")?;
+
+    let mut cmd = command(&["--resume"])?;
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    let mut lines: Vec<&str> = output.lines().collect();
+    lines.sort_unstable();
+
+    let mut expected = [
+        format!("1:2:2:2:2:2:2:{}", first.display()),
+        format!("1:10:10:10:10:10:10:{}", second.display()),
+    ];
+
+    expected.sort_unstable();
+
+    assert_eq!(lines, expected);
+
+    assert!(!file.exists(), "checkpoint is removed when finished");
+
+    Ok(())
+}
+
+#[test]
+fn resume_without_checkpoint() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let work = tempdir()?;
+    let file = work.path().join("checkpoint");
+
+    fs::write(dir.path().join("a"), "a")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--resume", "--checkpoint"])
+        .arg(&file)
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    let expected = format!("1:1:1:1:1:1:1:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    assert!(!file.exists(), "checkpoint is removed when finished");
+
+    Ok(())
+}
+
+#[test]
+fn resume_requires_checkpoint() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--resume", "1", "--"]).arg(dir.path());
+
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn resume_invalid_checkpoint() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let work = tempdir()?;
+    let file = work.path().join("checkpoint");
+
+    fs::write(&file, "something else\n")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--resume", "--checkpoint"])
+        .arg(&file)
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid checkpoint"));
+
+    Ok(())
+}
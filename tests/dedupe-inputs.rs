@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn nested_inputs_scanned_twice() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let sub = dir.path().join("sub");
+
+    fs::create_dir(&sub)?;
+    fs::write(sub.join("a"), "a")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "1", "--"])
+        .arg(dir.path())
+        .arg(&sub);
+
+    // input directories are not reported in a particular order
+    let parent = format!("1:1:1:1:1:1:1:{}\n", dir.path().display());
+    let nested = format!("1:1:1:1:1:1:1:{}\n", sub.display());
+
+    cmd.assert().success().stdout(
+        predicate::str::contains(parent)
+            .and(predicate::str::contains(nested))
+            .and(predicate::function(|out: &str| out.lines().count() == 2)),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dedupe_inputs() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let sub = dir.path().join("sub");
+
+    fs::create_dir(&sub)?;
+    fs::write(sub.join("a"), "a")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--dedupe-inputs", "1", "--"])
+        .arg(&sub)
+        .arg(dir.path())
+        .arg(dir.path());
+
+    let expected = format!("1:1:1:1:1:1:1:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
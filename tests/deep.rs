@@ -0,0 +1,47 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn deep_tree() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    // deeper than PATH_MAX, built from chains short enough to be created by
+    // path, each moved into the deepest directory of the next one
+    let tree = dir.path().join("tree");
+
+    for chain in 0..3 {
+        let mut path = dir.path().join("chain");
+
+        for _ in 0..1000 {
+            path.push("d");
+        }
+
+        fs::create_dir_all(&path)?;
+
+        if chain == 0 {
+            fs::write(path.join("file"), "x")?;
+        } else {
+            fs::rename(&tree, path.join("tree"))?;
+        }
+
+        fs::rename(dir.path().join("chain"), &tree)?;
+    }
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--threads", "2", "1", "--"])
+        .arg(dir.path());
+
+    let expected = format!("1:1:1:1:1:1:1:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    dir.close()?;
+
+    Ok(())
+}
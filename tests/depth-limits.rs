@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::{TempDir, tempdir};
+
+fn tree() -> Result<TempDir, Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::create_dir_all(dir.path().join("x/y"))?;
+    fs::write(dir.path().join("a"), "a")?;
+    fs::write(dir.path().join("x/b"), "bb")?;
+    fs::write(dir.path().join("x/y/c"), "cccc")?;
+
+    Ok(dir)
+}
+
+fn depth_limits(args: &[&str], bytes: u64) -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline"])
+        .args(args)
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    let files = u64::from(bytes.count_ones());
+
+    let settings: String = args
+        .chunks(2)
+        .map(|arg| {
+            let name = arg[0].trim_start_matches("--").replace('-', "_");
+            format!("#setting:{name}:{}\n", arg[1])
+        })
+        .collect();
+
+    let expected = format!(
+        "{settings}1:{bytes}:{bytes}:{bytes}:{files}:{files}:{files}:{}\n",
+        dir.path().display()
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
+
+#[test]
+fn max_depth() -> Result<(), Box<dyn Error>> {
+    depth_limits(&["--max-depth", "2"], 3)
+}
+
+#[test]
+fn min_depth() -> Result<(), Box<dyn Error>> {
+    depth_limits(&["--min-depth", "2"], 6)
+}
+
+#[test]
+fn min_and_max_depth() -> Result<(), Box<dyn Error>> {
+    depth_limits(&["--min-depth", "2", "--max-depth", "2"], 2)
+}
+
+#[test]
+fn min_depth_larger_than_max_depth() -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--min-depth", "3", "--max-depth", "2", "1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("larger than maximum depth"));
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn depth() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::create_dir_all(dir.path().join("a/b"))?;
+    fs::create_dir_all(dir.path().join("c"))?;
+
+    fs::write(dir.path().join("foo"), "foo")?;
+    fs::write(dir.path().join("a/bar"), "bar")?;
+    fs::write(dir.path().join("a/b/baz"), "baz")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--depth", "1", "1", "--"])
+        .arg(dir.path());
+
+    let dir = dir.path().display();
+
+    let expected = format!(
+        "\
+1:9:9:9:3:3:3:0:{dir}
+1:6:6:6:2:2:2:1:{dir}/a
+1:0:0:0:0:0:0:1:{dir}/c
+"
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
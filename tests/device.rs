@@ -0,0 +1,38 @@
+#![cfg(target_os = "linux")]
+
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn by_device() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("foo"), "foo")?;
+    fs::write(dir.path().join("bar"), "bar")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--by-device", "1", "--"])
+        .arg(dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 1);
+
+    // the mount point depends on the environment, only check it contains
+    // the input directory
+    let fields: Vec<&str> = lines[0].splitn(8, ':').collect();
+    assert_eq!(fields[..7].join(":"), "1:6:6:6:2:2:2");
+
+    let (device, path) = fields[7].split_once(':').unwrap();
+    assert!(dir.path().canonicalize()?.starts_with(device));
+    assert_eq!(path, dir.path().display().to_string());
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+#![cfg(target_os = "linux")]
+
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+/// Returns `true` if `/dev/pts` is a mount of type `devpts`.
+fn devpts_mounted() -> bool {
+    fs::read_to_string("/proc/self/mountinfo").is_ok_and(|mountinfo| {
+        mountinfo.lines().any(|line| {
+            line.contains(" /dev/pts ") && line.contains(" - devpts ")
+        })
+    })
+}
+
+#[test]
+fn exclude_fstype() -> Result<(), Box<dyn Error>> {
+    if !devpts_mounted() {
+        return Ok(());
+    }
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--debug", "--exclude-fstype", "nfs,devpts", "1", "--"])
+        .arg("/dev");
+
+    cmd.assert().success().stderr(predicate::str::contains(
+        "skipping excluded mount: /dev/pts",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn exclude_mount() -> Result<(), Box<dyn Error>> {
+    if !devpts_mounted() {
+        return Ok(());
+    }
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--debug", "--exclude-mount", "/dev/pts/", "1", "--"])
+        .arg("/dev");
+
+    cmd.assert().success().stderr(predicate::str::contains(
+        "skipping excluded mount: /dev/pts",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn exclude_mount_relative() -> Result<(), Box<dyn Error>> {
+    if !devpts_mounted() {
+        return Ok(());
+    }
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.current_dir("/dev")
+        .args(["--debug", "--exclude-mount", "pts", "1", "--"])
+        .arg("/dev");
+
+    cmd.assert().success().stderr(predicate::str::contains(
+        "skipping excluded mount: /dev/pts",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn exclude_mount_missing() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.arg("--exclude-mount")
+        .arg(dir.path().join("missing"))
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("error resolving exclude mount"));
+
+    Ok(())
+}
+
+#[test]
+fn exclude_other_mount() -> Result<(), Box<dyn Error>> {
+    if !devpts_mounted() {
+        return Ok(());
+    }
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--debug", "--exclude-fstype", "nfs", "1", "--"])
+        .arg("/dev");
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("skipping excluded mount").not());
+
+    Ok(())
+}
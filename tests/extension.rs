@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn top_extensions() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("a.bam"), "bam")?;
+    fs::write(dir.path().join("b.BAM"), "bam")?;
+    fs::write(dir.path().join("c.fastq.gz"), "fastq")?;
+    fs::write(dir.path().join("d.nc"), "nc")?;
+    fs::write(dir.path().join("Makefile"), "all")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--top-extensions", "2", "1", "--"])
+        .arg(dir.path());
+
+    let dir = dir.path().display();
+
+    let expected = format!(
+        "\
+1:6:6:6:2:2:2:.bam:{dir}
+1:5:5:5:1:1:1:.fastq.gz:{dir}
+1:5:5:5:2:2:2:other:{dir}
+"
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
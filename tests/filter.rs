@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::{TempDir, tempdir};
+
+fn tree() -> Result<TempDir, Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::create_dir_all(dir.path().join(".git/objects"))?;
+    fs::create_dir_all(dir.path().join("data"))?;
+
+    fs::write(dir.path().join(".git/objects/object"), "object")?;
+    fs::write(dir.path().join("data/foo.txt"), "foo")?;
+    fs::write(dir.path().join("data/bar.tmp"), "bar")?;
+    fs::write(dir.path().join("baz.txt"), "baz")?;
+
+    Ok(dir)
+}
+
+#[test]
+fn exclude() -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline"])
+        .args(["--exclude", ".git", "--exclude", "*.tmp"])
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    let expected = format!("1:6:6:6:2:2:2:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    dir.close()?;
+
+    Ok(())
+}
+
+#[test]
+fn exclude_from() -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let patterns = dir.path().join("patterns");
+    fs::write(&patterns, "# version control\n.git\n\n/data\n")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--exclude-from"])
+        .arg(&patterns)
+        .args(["--exclude", "patterns", "1", "--"])
+        .arg(dir.path());
+
+    let expected = format!("1:3:3:3:1:1:1:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    dir.close()?;
+
+    Ok(())
+}
+
+#[test]
+fn include() -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--include", "*.txt"])
+        .args(["--exclude", "baz.txt", "1", "--"])
+        .arg(dir.path());
+
+    let expected = format!("1:3:3:3:1:1:1:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    dir.close()?;
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+#![cfg(target_family = "unix")]
+
+use std::error::Error;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn follow_link_dir() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+    let shared = tempdir()?;
+
+    fs::write(shared.path().join("file"), "text")?;
+    symlink(shared.path(), dir.path().join("link"))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args([
+        "--format",
+        "oneline",
+        "--counts",
+        "--follow-symlinks",
+        "1",
+        "--",
+    ])
+    .arg(dir.path());
+
+    let expected =
+        format!("1:4:4:4:1:1:1:0:1:0:0:0:0:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args([
+        "--format",
+        "oneline",
+        "--counts",
+        "--follow-symlinks=root",
+        "1",
+        "--",
+    ])
+    .arg(dir.path());
+
+    let expected =
+        format!("1:0:0:0:0:0:0:0:0:1:0:0:0:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    dir.close()?;
+    shared.close()?;
+
+    Ok(())
+}
+
+#[test]
+fn follow_link_loop() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let subdir = dir.path().join("subdir");
+    fs::create_dir(&subdir)?;
+    fs::write(subdir.join("file"), "text")?;
+    symlink(dir.path(), subdir.join("loop"))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args([
+        "--format",
+        "oneline",
+        "--counts",
+        "--follow-symlinks=root",
+        "1",
+        "--",
+    ])
+    .arg(dir.path());
+
+    let expected =
+        format!("1:4:4:4:1:1:1:0:1:0:0:0:0:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    dir.close()?;
+
+    Ok(())
+}
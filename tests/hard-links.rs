@@ -0,0 +1,79 @@
+#![cfg(target_family = "unix")]
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn count_hard_links_once() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let path = dir.path().join("file");
+    let mut file = File::create(&path)?;
+    writeln!(file, "text")?;
+
+    fs::hard_link(&path, dir.path().join("link1"))?;
+    fs::hard_link(&path, dir.path().join("link2"))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--counts", "1", "--"])
+        .arg(dir.path());
+
+    let expected =
+        format!("1:5:5:5:1:1:1:2:0:0:0:0:0:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    drop(file);
+    dir.close()?;
+
+    Ok(())
+}
+
+#[test]
+fn count_hard_links_once_across_dirs() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let foo = dir.path().join("foo");
+    let bar = dir.path().join("bar");
+    fs::create_dir(&foo)?;
+    fs::create_dir(&bar)?;
+
+    let path = foo.join("file");
+    let mut file = File::create(&path)?;
+    writeln!(file, "text")?;
+
+    fs::hard_link(&path, bar.join("link"))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--counts", "1", "--"])
+        .arg(&foo)
+        .arg(&bar);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    assert!(
+        output.contains(&format!(
+            "1:5:5:5:1:1:1:0:0:0:0:0:0:{}\n",
+            foo.display()
+        ))
+    );
+    assert!(
+        output.contains(&format!(
+            "1:0:0:0:0:0:0:1:0:0:0:0:0:{}\n",
+            bar.display()
+        ))
+    );
+
+    drop(file);
+    dir.close()?;
+
+    Ok(())
+}
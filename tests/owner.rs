@@ -0,0 +1,87 @@
+#![cfg(target_family = "unix")]
+
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn by_user() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::create_dir(dir.path().join("sub"))?;
+    fs::write(dir.path().join("foo"), "foo")?;
+    fs::write(dir.path().join("sub/bar"), "bar")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args([
+        "--format",
+        "oneline",
+        "--by-user",
+        "--depth",
+        "1",
+        "1",
+        "--",
+    ])
+    .arg(dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    // the user name depends on the environment, only check it is there
+    let lines: Vec<String> = output
+        .lines()
+        .map(|line| {
+            let mut fields: Vec<&str> = line.splitn(10, ':').collect();
+            assert!(!fields.remove(7).is_empty());
+            fields.join(":")
+        })
+        .collect();
+
+    let dir = dir.path().display();
+
+    let expected = [
+        format!("1:6:6:6:2:2:2:0:{dir}"),
+        format!("1:3:3:3:1:1:1:1:{dir}/sub"),
+    ];
+
+    assert_eq!(lines, expected);
+
+    Ok(())
+}
+
+#[test]
+fn by_user_and_group() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("foo"), "foo")?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--by-user", "--by-group", "1", "--"])
+        .arg(dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    // the user row has no group and the group row has no user
+    let lines: Vec<(bool, bool, String)> = output
+        .lines()
+        .map(|line| {
+            let mut fields: Vec<&str> = line.splitn(10, ':').collect();
+            let group = fields.remove(8);
+            let user = fields.remove(7);
+            (user.is_empty(), group.is_empty(), fields.join(":"))
+        })
+        .collect();
+
+    let line = format!("1:3:3:3:1:1:1:{}", dir.path().display());
+
+    let expected = [(false, true, line.clone()), (true, false, line)];
+
+    assert_eq!(lines, expected);
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn reference_time() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("file"), vec![0; 100])?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline"])
+        .args(["--reference-time", "2100-01-01T00:00:00Z"])
+        .args(["1", "36500", "--"])
+        .arg(dir.path());
+
+    let dir = dir.path().display();
+
+    let expected = format!(
+        "\
+1:100:0:0:1:0:0:{dir}
+36500:100:100:100:1:1:1:{dir}
+"
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
+
+#[test]
+fn reference_time_invalid() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--reference-time", "yesterday", "1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid timestamp: yesterday"));
+
+    Ok(())
+}
+
+#[test]
+fn reference_time_out_of_range() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--reference-time=-9223372036854775000", "1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("timestamp out of range"));
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--reference-time=0", "5000000", "--"])
+        .arg(dir.path());
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "age of 5000000 days is out of range of the reference time",
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn size_buckets() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("empty"), "")?;
+    fs::write(dir.path().join("small"), vec![0; 100])?;
+    fs::write(dir.path().join("medium"), vec![0; 1024])?;
+    fs::write(dir.path().join("large"), vec![0; 5000])?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--size-buckets", "4KiB,1K"])
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    let dir = dir.path().display();
+
+    let expected = format!(
+        "\
+1:100:100:100:2:2:2:<1KiB:{dir}
+1:1024:1024:1024:1:1:1:<4KiB:{dir}
+1:5000:5000:5000:1:1:1:>=4KiB:{dir}
+"
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
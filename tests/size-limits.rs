@@ -0,0 +1,83 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::{TempDir, tempdir};
+
+fn tree() -> Result<TempDir, Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::create_dir(dir.path().join("x"))?;
+    fs::write(dir.path().join("a"), "a")?;
+    fs::write(dir.path().join("x/b"), "bb")?;
+    fs::write(dir.path().join("x/c"), vec![0; 2048])?;
+
+    Ok(dir)
+}
+
+fn size_limits(
+    args: &[&str],
+    settings: &str,
+    bytes: u64,
+    files: u64,
+) -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--size", "apparent"])
+        .args(args)
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    let expected = format!(
+        "{settings}1:{bytes}:{bytes}:{bytes}:{files}:{files}:{files}:{}\n",
+        dir.path().display()
+    );
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(())
+}
+
+#[test]
+fn min_size() -> Result<(), Box<dyn Error>> {
+    size_limits(&["--min-size", "2"], "#setting:min_size:2\n", 2050, 2)
+}
+
+#[test]
+fn max_size() -> Result<(), Box<dyn Error>> {
+    size_limits(&["--max-size", "2"], "#setting:max_size:2\n", 3, 2)
+}
+
+#[test]
+fn min_size_with_unit() -> Result<(), Box<dyn Error>> {
+    size_limits(&["--min-size", "1KiB"], "#setting:min_size:1024\n", 2048, 1)
+}
+
+#[test]
+fn min_and_max_size() -> Result<(), Box<dyn Error>> {
+    size_limits(
+        &["--min-size", "2", "--max-size", "2"],
+        "#setting:min_size:2\n#setting:max_size:2\n",
+        2,
+        1,
+    )
+}
+
+#[test]
+fn min_size_larger_than_max_size() -> Result<(), Box<dyn Error>> {
+    let dir = tree()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--min-size", "1M", "--max-size", "1K", "1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("larger than maximum size"));
+
+    Ok(())
+}
@@ -79,3 +79,26 @@ fn skip_link_file() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[cfg(target_family = "unix")]
+#[test]
+fn skip_special_file() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("file"), "foo")?;
+    let socket = os::unix::net::UnixListener::bind(dir.path().join("socket"))?;
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["--format", "oneline", "--counts", "1", "--"])
+        .arg(dir.path());
+
+    let expected =
+        format!("1:3:3:3:1:1:1:0:0:0:0:0:1:{}\n", dir.path().display());
+
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    drop(socket);
+    dir.close()?;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn parallel_equals_sequential() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    for i in 0..8 {
+        let subdir = dir.path().join(format!("dir{i}")).join("nested");
+        fs::create_dir_all(&subdir)?;
+
+        for j in 0..8 {
+            let mut file = File::create(subdir.join(format!("file{j}")))?;
+            writeln!(file, "{}", "x".repeat(i * j))?;
+        }
+    }
+
+    let output = |threads: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut cmd = Command::cargo_bin(crate_name!())?;
+        cmd.args(["--format", "oneline", "--threads", threads])
+            .args(["1", "365", "--"])
+            .arg(dir.path());
+
+        Ok(cmd.assert().success().get_output().stdout.clone())
+    };
+
+    let sequential = output("1")?;
+    let parallel = output("4")?;
+
+    assert!(!sequential.is_empty());
+    assert_eq!(sequential, parallel);
+
+    dir.close()?;
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+/// Returns how long a successful run took, checking it still counts `files`
+/// files of one byte each.
+fn elapsed(
+    dir: &Path,
+    args: &[&str],
+    files: usize,
+) -> Result<Duration, Box<dyn Error>> {
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--threads", "4"])
+        .args(args)
+        .args(["1", "--"])
+        .arg(dir);
+
+    let expected = format!(
+        "1:{files}:{files}:{files}:{files}:{files}:{files}:{}\n",
+        dir.display()
+    );
+
+    let start = Instant::now();
+    cmd.assert().success().stdout(predicate::eq(expected));
+
+    Ok(start.elapsed())
+}
+
+#[test]
+fn max_stats_per_second() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    for i in 0..21 {
+        fs::write(dir.path().join(i.to_string()), "x")?;
+    }
+
+    // 21 stats spaced out to 40 per second take at least half a second
+    let elapsed = elapsed(dir.path(), &["--max-stats-per-second", "40"], 21)?;
+    assert!(elapsed >= Duration::from_millis(450), "{elapsed:?}");
+
+    Ok(())
+}
+
+#[test]
+fn max_dirs_per_second() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    for i in 0..5 {
+        let sub = dir.path().join(i.to_string());
+        fs::create_dir(&sub)?;
+        fs::write(sub.join("file"), "x")?;
+    }
+
+    // 6 directories spaced out to 10 per second take at least half a second
+    let elapsed = elapsed(dir.path(), &["--max-dirs-per-second", "10"], 5)?;
+    assert!(elapsed >= Duration::from_millis(450), "{elapsed:?}");
+
+    Ok(())
+}
+
+#[test]
+fn adaptive_throttle() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    for i in 0..1000 {
+        fs::write(dir.path().join(i.to_string()), "x")?;
+    }
+
+    elapsed(dir.path(), &["--adaptive-throttle"], 1000)?;
+
+    Ok(())
+}
+
+#[test]
+fn zero_per_second() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--max-stats-per-second", "0", "1", "--"])
+        .arg(dir.path());
+
+    cmd.assert().failure();
+
+    Ok(())
+}
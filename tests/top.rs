@@ -0,0 +1,64 @@
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use assert_cmd::crate_name;
+use assert_cmd::prelude::*;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn top() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("a"), vec![0; 1])?;
+    fs::write(dir.path().join("b"), vec![0; 3])?;
+    fs::write(dir.path().join("c"), vec![0; 2])?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--top", "2"])
+        .args(["--reference-time", "2100-01-01T00:00:00Z"])
+        .args(["1", "--"])
+        .arg(dir.path());
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+
+    // the times depend on the environment, only check they are there
+    let lines: Vec<String> = output
+        .lines()
+        .filter(|line| line.starts_with("#top:"))
+        .map(|line| {
+            let mut fields: Vec<&str> = line.splitn(7, ':').collect();
+            assert!(!fields.remove(4).is_empty());
+            assert!(!fields.remove(4).is_empty());
+            fields.join(":")
+        })
+        .collect();
+
+    let dir = dir.path().display();
+
+    let expected =
+        [format!("#top:1:1:3:{dir}/b"), format!("#top:1:2:2:{dir}/c")];
+
+    assert_eq!(lines, expected);
+
+    Ok(())
+}
+
+#[test]
+fn top_none_past_threshold() -> Result<(), Box<dyn Error>> {
+    let dir = tempdir()?;
+
+    fs::write(dir.path().join("a"), vec![0; 1])?;
+
+    let mut cmd = Command::cargo_bin(crate_name!())?;
+    cmd.args(["--format", "oneline", "--top", "2", "1", "--"])
+        .arg(dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("#top:").not());
+
+    Ok(())
+}